@@ -1,53 +1,1265 @@
+use crate::camera::{Camera, Follow};
+use crate::collision::CollisionMode;
+use crate::diagnostics::{relative_drift, Diagnostics};
+use crate::encounter::{EncounterKind, Watch};
+use crate::generators::{Generator, MassDistribution, Pattern};
+use crate::gravity::ForceSolver;
+use crate::integrator::IntegratorKind;
+use crate::orbit::Orbit;
 use crate::planet::Planet;
+use crate::plots::History;
+use crate::prediction::{Prediction, PredictionScope};
+use crate::presets::Preset;
+use crate::scene::{Scene, View};
+use crate::selection::Selection;
+use crate::simulation::{Simulation, SimulationEvent};
+use crate::timestep::FixedTimestep;
+use crate::trail::{FrameHistory, TrailLimit, TrailStyle};
+use crate::undo::{Change, UndoStack};
 use eframe::{egui, epi};
-use rand::prelude::*;
+
+/// Simulated time between the points of the trajectory preview.
+const PREVIEW_INTERVAL: f32 = 1.0 / 60.0;
+
+/// How many seconds simulation events stay listed in the panel.
+const EVENT_LIFETIME: f64 = 5.0;
+
+/// How many pairs of bodies the continuous prediction may work through per
+/// frame on top of keeping pace with the live simulation, which bounds how
+/// fast it reaches further ahead in crowded scenes.
+const PREDICTION_BUDGET: usize = 200_000;
+
+/// Screen radius of the handle at the tip of a velocity arrow.
+const HANDLE_RADIUS: f32 = 5.0;
+
+/// Which part of an existing planet is being dragged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handle {
+    /// The planet itself, to move it.
+    Body,
+    /// The tip of its velocity arrow, to re-aim it.
+    Velocity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Grab {
+    id: i32,
+    handle: Handle,
+    /// Whether the simulation was running before the drag paused it.
+    resume: bool,
+    /// Whether it has actually been dragged anywhere yet.
+    moved: bool,
+}
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 #[cfg_attr(feature = "persistence", serde(default))] // if we add new fields, give them default values when deserializing old state
 pub struct App {
-    particles: Vec<Planet>,
+    simulation: Simulation,
     #[cfg_attr(feature = "persistence", serde(skip))]
-    gravity: f32,
+    stepper: FixedTimestep,
     #[cfg_attr(feature = "persistence", serde(skip))]
     size: f32,
     #[cfg_attr(feature = "persistence", serde(skip))]
     mass: f32,
     #[cfg_attr(feature = "persistence", serde(skip))]
     creating: Option<egui::Pos2>,
+    /// The existing planet being dragged around, if any.
     #[cfg_attr(feature = "persistence", serde(skip))]
-    last_id: i32,
+    grab: Option<Grab>,
     #[cfg_attr(feature = "persistence", serde(skip))]
-    selected: i32,
+    selection: Selection,
+    /// Where on screen the rubber band for selecting planets was started.
     #[cfg_attr(feature = "persistence", serde(skip))]
-    force_fields: bool,
+    band: Option<egui::Pos2>,
+    /// Whether the inspector window of the selected planets is open.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    inspecting: bool,
     #[cfg_attr(feature = "persistence", serde(skip))]
-    min_trail_update: f32,
+    camera: Camera,
+    /// What the camera moves along with.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    follow: Follow,
+    /// Fit the camera to the bodies on the next frame, once the size of the
+    /// screen is known.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    fit_requested: bool,
+    /// Whether the middle button is dragging the view around.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    panning: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    force_fields: bool,
     #[cfg_attr(feature = "persistence", serde(skip))]
     arrow_size: f32,
+    /// Draw the Keplerian orbits of the selected planets.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    show_orbits: bool,
+    /// Draw trails as seen from the frame the camera follows.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    relative_trails: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    frames: FrameHistory,
     #[cfg_attr(feature = "persistence", serde(skip))]
     preview_length: i32,
+    /// Whose future paths are always shown.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    predicting: PredictionScope,
+    /// How many seconds ahead the always shown paths go.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    prediction_horizon: f32,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    prediction: Prediction,
     #[cfg_attr(feature = "persistence", serde(skip))]
     paused: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    events: Vec<(f64, SimulationEvent)>,
+    /// What the diagnostics panel measures drift against.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    baseline: Option<Diagnostics>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    show_plots: bool,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    history: History,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    edits: UndoStack,
+    scene_path: String,
+    /// The outcome of the last save or load.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    scene_status: Option<String>,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    preset: Preset,
+    generator: Generator,
+    /// Clicking on the canvas spawns the generator's pattern instead of a
+    /// single planet.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    spawning: bool,
+    /// A scene to open once the app is set up, e.g. from a link.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    startup_scene: Option<Scene>,
 }
 
 impl Default for App {
     fn default() -> Self {
         Self {
-            particles: Vec::new(),
-            gravity: 20.0,
+            simulation: Simulation::default(),
+            stepper: FixedTimestep::default(),
             size: 5.0,
             mass: 5.0,
             creating: None,
-            last_id: 0,
-            selected: -1,
+            grab: None,
+            selection: Selection::default(),
+            band: None,
+            inspecting: false,
+            camera: Camera::default(),
+            follow: Follow::Free,
+            fit_requested: false,
+            panning: false,
             force_fields: false,
-            min_trail_update: 0.1,
             arrow_size: 10.0,
+            show_orbits: false,
+            relative_trails: false,
+            frames: FrameHistory::default(),
             preview_length: 100,
+            predicting: PredictionScope::default(),
+            prediction_horizon: 5.0,
+            prediction: Prediction::default(),
             paused: false,
+            events: Vec::new(),
+            baseline: None,
+            show_plots: false,
+            history: History::default(),
+            edits: UndoStack::default(),
+            scene_path: "scene.json".to_owned(),
+            scene_status: None,
+            preset: Preset::default(),
+            generator: Generator::default(),
+            spawning: false,
+            startup_scene: None,
+        }
+    }
+}
+
+/// Whether `pos` is on the bare background rather than over a window or popup.
+fn on_canvas(ctx: &egui::CtxRef, pos: egui::Pos2) -> bool {
+    ctx.layer_id_at(pos)
+        .is_none_or(|layer| layer.order == egui::Order::Background)
+}
+
+/// A combo box for picking one of `options`. Sets `over_popup` while the
+/// pointer is over its open popup, which isn't part of the returned response.
+fn choice<T: Copy + PartialEq>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut T,
+    options: &[T],
+    name: fn(T) -> &'static str,
+    over_popup: &mut bool,
+) -> egui::Response {
+    let combo = egui::ComboBox::from_label(label)
+        .selected_text(name(*value))
+        .show_ui(ui, |ui| {
+            let mut hovered = false;
+            for option in options {
+                hovered |= ui.selectable_value(value, *option, name(*option)).hovered();
+            }
+            hovered
+        });
+    *over_popup |= combo.inner == Some(true);
+    combo.response
+}
+
+/// The settings of a trail, returning the responses of everything involved.
+fn trail_style_ui(
+    ui: &mut egui::Ui,
+    style: &mut TrailStyle,
+    over_popup: &mut bool,
+) -> Vec<egui::Response> {
+    let mut responses = vec![
+        ui.checkbox(&mut style.visible, "show trail"),
+        choice(
+            ui,
+            "length in",
+            &mut style.limit,
+            &TrailLimit::ALL,
+            TrailLimit::name,
+            over_popup,
+        ),
+    ];
+    responses.push(match style.limit {
+        TrailLimit::Points => ui.add(
+            egui::Slider::new(&mut style.max_points, 2..=10_000)
+                .logarithmic(true)
+                .text("points"),
+        ),
+        TrailLimit::Seconds => ui.add(
+            egui::Slider::new(&mut style.max_seconds, 0.1..=120.0)
+                .logarithmic(true)
+                .text("seconds"),
+        ),
+    });
+    responses.extend([
+        ui.add(egui::Slider::new(&mut style.width, 0.5..=10.0).text("width")),
+        ui.checkbox(&mut style.fade, "fade out"),
+        ui.checkbox(&mut style.taper, "taper"),
+    ]);
+    responses
+}
+
+/// Puts `fragment` into the address bar and returns the whole link.
+#[cfg(target_arch = "wasm32")]
+fn set_url_fragment(fragment: &str) -> Option<String> {
+    let location = web_sys::window()?.location();
+    location.set_hash(fragment).ok()?;
+    location.href().ok()
+}
+
+/// A combo box for picking one of `bodies` by id, like [`choice`].
+fn planet_choice(
+    ui: &mut egui::Ui,
+    label: &str,
+    id: &mut i32,
+    bodies: &[Planet],
+    over_popup: &mut bool,
+) -> egui::Response {
+    let combo = egui::ComboBox::from_label(label)
+        .selected_text(
+            bodies
+                .iter()
+                .find(|p| p.id == *id)
+                .map_or_else(|| format!("planet {}", id), Planet::label),
+        )
+        .show_ui(ui, |ui| {
+            let mut hovered = false;
+            for p in bodies {
+                hovered |= ui.selectable_value(id, p.id, p.label()).hovered();
+            }
+            hovered
+        });
+    *over_popup |= combo.inner == Some(true);
+    combo.response
+}
+
+impl App {
+    /// An app that opens the scene in `link`, as made by [`Scene::to_link`].
+    pub fn from_link(link: &str) -> Self {
+        let mut app = Self::default();
+        match Scene::from_link(link) {
+            Ok(scene) => app.startup_scene = Some(scene),
+            Err(err) => app.scene_status = Some(format!("couldn't open the link: {}", err)),
+        }
+        app
+    }
+
+    /// The collapsible panel of conserved quantities, returning the responses
+    /// of everything in it.
+    fn diagnostics_ui(&mut self, ui: &mut egui::Ui) -> Vec<egui::Response> {
+        let simulation = &self.simulation;
+        let baseline = &mut self.baseline;
+        let panel = egui::CollapsingHeader::new("diagnostics")
+            .default_open(false)
+            .show(ui, |ui| {
+                let now = Diagnostics::measure(simulation);
+                // Adding or losing bodies changes the totals for real, so
+                // start measuring drift again from there.
+                if baseline.is_none_or(|b| b.bodies != now.bodies) {
+                    *baseline = Some(now);
+                }
+                let then = baseline.unwrap_or(now);
+                egui::Grid::new("diagnostics_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("energy");
+                        ui.label(format!("{:.4e}", now.energy()));
+                        ui.label(format!(
+                            "{:+.3e}",
+                            relative_drift(now.energy(), then.energy())
+                        ));
+                        ui.end_row();
+
+                        ui.label("kinetic");
+                        ui.label(format!("{:.4e}", now.kinetic));
+                        ui.label(format!(
+                            "{:+.3e}",
+                            relative_drift(now.kinetic, then.kinetic)
+                        ));
+                        ui.end_row();
+
+                        ui.label("potential");
+                        ui.label(format!("{:.4e}", now.potential));
+                        ui.label(format!(
+                            "{:+.3e}",
+                            relative_drift(now.potential, then.potential)
+                        ));
+                        ui.end_row();
+
+                        ui.label("momentum");
+                        ui.label(format!("({:.3e}, {:.3e})", now.momentum.x, now.momentum.y));
+                        ui.label(format!("Δ {:.3e}", (now.momentum - then.momentum).length()));
+                        ui.end_row();
+
+                        ui.label("angular momentum");
+                        ui.label(format!("{:.4e}", now.angular_momentum));
+                        ui.label(format!(
+                            "{:+.3e}",
+                            relative_drift(now.angular_momentum, then.angular_momentum)
+                        ));
+                        ui.end_row();
+
+                        ui.label("center of mass");
+                        ui.label(format!(
+                            "({:.1}, {:.1})",
+                            now.center_of_mass.x, now.center_of_mass.y
+                        ));
+                        ui.end_row();
+                    });
+                ui.button("reset drift").clicked()
+            });
+        if panel.body_returned == Some(true) {
+            self.baseline = None;
+        }
+        let mut responses = vec![panel.header_response];
+        responses.extend(panel.body_response);
+        responses
+    }
+
+    /// The reference frame the camera follows and the planets that make it
+    /// up, returning the responses of everything involved.
+    fn frame_ui(&mut self, ui: &mut egui::Ui, over_popup: &mut bool) -> Vec<egui::Response> {
+        let bodies = self.simulation.bodies();
+        let mut by_mass = bodies.iter().collect::<Vec<_>>();
+        by_mass.sort_by(|a, b| b.mass.total_cmp(&a.mass));
+        let heaviest = by_mass.first().map_or(-1, |p| p.id);
+        let selected = self.selection.primary();
+        let options = [
+            Follow::Free,
+            Follow::Body(selected.unwrap_or(heaviest)),
+            Follow::Barycenter,
+            Follow::Subset(self.selection.ids().to_vec()),
+            Follow::CoRotating(
+                heaviest,
+                selected
+                    .filter(|id| *id != heaviest)
+                    .or_else(|| by_mass.get(1).map(|p| p.id))
+                    .unwrap_or(-1),
+            ),
+        ];
+
+        let follow = &mut self.follow;
+        let combo = egui::ComboBox::from_label("frame")
+            .selected_text(follow.name())
+            .show_ui(ui, |ui| {
+                let mut hovered = false;
+                for option in options {
+                    let current = std::mem::discriminant(&option) == std::mem::discriminant(follow);
+                    let label = ui.selectable_label(current, option.name());
+                    if label.clicked() && !current {
+                        *follow = option;
+                    }
+                    hovered |= label.hovered();
+                }
+                hovered
+            });
+        *over_popup |= combo.inner == Some(true);
+        let mut responses = vec![combo.response];
+
+        match follow {
+            Follow::Body(id) => {
+                responses.push(planet_choice(ui, "planet", id, bodies, over_popup));
+            }
+            Follow::Subset(ids) => {
+                let panel = egui::CollapsingHeader::new("subset").show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(100.0)
+                        .show(ui, |ui| {
+                            let mut responses = Vec::new();
+                            for p in bodies {
+                                let mut member = ids.contains(&p.id);
+                                let response = ui.checkbox(&mut member, p.label());
+                                if response.changed() {
+                                    if member {
+                                        ids.push(p.id);
+                                    } else {
+                                        ids.retain(|id| *id != p.id);
+                                    }
+                                }
+                                responses.push(response);
+                            }
+                            responses
+                        })
+                });
+                responses.push(panel.header_response);
+                responses.extend(panel.body_response);
+                responses.extend(panel.body_returned.into_iter().flatten());
+            }
+            Follow::CoRotating(first, second) => {
+                responses.push(planet_choice(ui, "from", first, bodies, over_popup));
+                responses.push(planet_choice(ui, "towards", second, bodies, over_popup));
+            }
+            Follow::Free | Follow::Barycenter => {}
+        }
+        responses
+    }
+
+    /// The window for looking at and editing the selected planet, with its
+    /// orbit around whatever it is mostly falling towards.
+    fn inspector_ui(&mut self, ctx: &egui::CtxRef) {
+        let Some(id) = self
+            .selection
+            .primary()
+            .filter(|_| self.selection.len() == 1)
+        else {
+            return;
+        };
+        let orbit = Orbit::around_dominant(&self.simulation, id);
+        let attractor = orbit
+            .and_then(|o| self.simulation.body(o.attractor))
+            .map(Planet::label);
+        // Edit a copy, so the planet as it was can go on the undo stack.
+        let Some(mut planet) = self.simulation.body(id).cloned() else {
+            return;
+        };
+        let trail = self.simulation.trail;
+        let mut changed = false;
+        let mut duplicate = false;
+        let mut delete = false;
+        egui::Window::new(planet.label())
+            .id(egui::Id::new("inspector"))
+            .open(&mut self.inspecting)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let (mut pos, old_pos) = (planet.pos, planet.pos);
+                egui::Grid::new("inspector_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("name");
+                        changed |= ui.text_edit_singleline(&mut planet.name).changed();
+                        ui.end_row();
+
+                        ui.label("position");
+                        ui.horizontal(|ui| {
+                            changed |= ui
+                                .add(egui::DragValue::new(&mut pos.x).prefix("x: "))
+                                .changed();
+                            changed |= ui
+                                .add(egui::DragValue::new(&mut pos.y).prefix("y: "))
+                                .changed();
+                        });
+                        ui.end_row();
+
+                        ui.label("velocity");
+                        ui.horizontal(|ui| {
+                            changed |= ui
+                                .add(egui::DragValue::new(&mut planet.vel.x).prefix("x: "))
+                                .changed();
+                            changed |= ui
+                                .add(egui::DragValue::new(&mut planet.vel.y).prefix("y: "))
+                                .changed();
+                        });
+                        ui.end_row();
+
+                        ui.label("mass");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut planet.mass)
+                                    .speed(0.1)
+                                    .clamp_range(0.01..=1e6),
+                            )
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("size");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut planet.size)
+                                    .speed(0.1)
+                                    .clamp_range(0.5..=500.0),
+                            )
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("color");
+                        changed |= egui::widgets::color_picker::color_edit_button_srgba(
+                            ui,
+                            &mut planet.color,
+                            egui::widgets::color_picker::Alpha::Opaque,
+                        )
+                        .changed();
+                        ui.end_row();
+                    });
+                // Jump straight there rather than sliding over from the last
+                // step, and don't draw a trail across the gap.
+                if pos != old_pos {
+                    planet.pos = pos;
+                    planet.prev_pos = pos;
+                    planet.trail.clear();
+                }
+
+                ui.separator();
+                match (orbit, attractor) {
+                    (Some(orbit), Some(attractor)) => {
+                        egui::Grid::new("orbit_grid").num_columns(2).show(ui, |ui| {
+                            ui.label("orbiting");
+                            ui.label(format!(
+                                "{} ({})",
+                                attractor,
+                                if orbit.clockwise {
+                                    "clockwise"
+                                } else {
+                                    "counterclockwise"
+                                }
+                            ));
+                            ui.end_row();
+
+                            ui.label("semi-major axis");
+                            ui.label(format!("{:.1}", orbit.semi_major_axis));
+                            ui.end_row();
+
+                            ui.label("eccentricity");
+                            ui.label(format!("{:.3}", orbit.eccentricity));
+                            ui.end_row();
+
+                            ui.label("periapsis");
+                            ui.label(format!("{:.1}", orbit.periapsis));
+                            ui.end_row();
+
+                            ui.label("apoapsis");
+                            ui.label(
+                                orbit
+                                    .apoapsis
+                                    .map_or("unbound".to_owned(), |r| format!("{:.1}", r)),
+                            );
+                            ui.end_row();
+
+                            ui.label("period");
+                            ui.label(
+                                orbit
+                                    .period
+                                    .map_or("none".to_owned(), |t| format!("{:.2} s", t)),
+                            );
+                            ui.end_row();
+                        });
+                    }
+                    _ => {
+                        ui.label("not orbiting anything heavier");
+                    }
+                }
+
+                ui.separator();
+                egui::CollapsingHeader::new("trail")
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        let mut own = planet.trail_style.is_some();
+                        if ui.checkbox(&mut own, "own settings").changed() {
+                            planet.trail_style = own.then_some(trail);
+                            changed = true;
+                        }
+                        if let Some(style) = &mut planet.trail_style {
+                            let mut over_popup = false;
+                            changed |= trail_style_ui(ui, style, &mut over_popup)
+                                .iter()
+                                .any(egui::Response::changed);
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    duplicate = ui.button("duplicate").clicked();
+                    delete = ui.button("delete").clicked();
+                });
+            });
+
+        let time = ctx.input().time;
+        if changed {
+            self.edits
+                .record(Change::Edit(planet.id), &self.simulation, time);
+            if let Some(p) = self.simulation.body_mut(planet.id) {
+                *p = planet.clone();
+            }
+        }
+        if duplicate {
+            self.edits
+                .record(Change::Duplicate(planet.id), &self.simulation, time);
+            let mut copy = planet;
+            copy.pos += egui::vec2(3.0 * copy.size, 0.0);
+            copy.prev_pos = copy.pos;
+            copy.trail.clear();
+            if !copy.name.is_empty() {
+                copy.name += " copy";
+            }
+            self.selection = Selection::single(self.simulation.add_body(copy));
+        } else if delete {
+            self.edits.record(Change::Delete, &self.simulation, time);
+            self.history.tracked.retain(|tracked| *tracked != id);
+            self.selection.delete(&mut self.simulation);
+        }
+    }
+
+    /// The window for editing all the selected planets at once, when there
+    /// are several.
+    fn selection_ui(&mut self, ctx: &egui::CtxRef) {
+        if self.selection.len() < 2 {
+            return;
+        }
+        let Some((_, center, vel)) = self.selection.center_of_mass(&self.simulation) else {
+            return;
+        };
+        let Some(primary) = self
+            .selection
+            .primary()
+            .and_then(|id| self.simulation.body(id))
+        else {
+            return;
+        };
+        let (mut new_center, mut new_vel) = (center, vel);
+        let (mut mass, mut color) = (primary.mass, primary.color);
+        let mut mass_changed = false;
+        let mut color_changed = false;
+        let mut bind = false;
+        let mut delete = false;
+        egui::Window::new(format!("{} planets", self.selection.len()))
+            .id(egui::Id::new("inspector"))
+            .open(&mut self.inspecting)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("selection_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("center of mass");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut new_center.x).prefix("x: "));
+                            ui.add(egui::DragValue::new(&mut new_center.y).prefix("y: "));
+                        });
+                        ui.end_row();
+
+                        ui.label("velocity");
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut new_vel.x).prefix("x: "));
+                            ui.add(egui::DragValue::new(&mut new_vel.y).prefix("y: "));
+                        });
+                        ui.end_row();
+
+                        ui.label("mass of each");
+                        mass_changed = ui
+                            .add(
+                                egui::DragValue::new(&mut mass)
+                                    .speed(0.1)
+                                    .clamp_range(0.01..=1e6),
+                            )
+                            .changed();
+                        ui.end_row();
+
+                        ui.label("color");
+                        color_changed = egui::widgets::color_picker::color_edit_button_srgba(
+                            ui,
+                            &mut color,
+                            egui::widgets::color_picker::Alpha::Opaque,
+                        )
+                        .changed();
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    bind = ui
+                        .button("make bound")
+                        .on_hover_text("stop the group drifting away from the rest")
+                        .clicked();
+                    delete = ui.button("delete").clicked();
+                });
+            });
+
+        let time = ctx.input().time;
+        if new_center != center || new_vel != vel || mass_changed || color_changed {
+            self.edits.record(Change::Group, &self.simulation, time);
+            let selection = &self.selection;
+            selection.translate(&mut self.simulation, new_center - center);
+            selection.add_velocity(&mut self.simulation, new_vel - vel);
+            if mass_changed {
+                selection.set_mass(&mut self.simulation, mass);
+            }
+            if color_changed {
+                selection.recolor(&mut self.simulation, color);
+            }
+        }
+        if bind {
+            self.edits.record(Change::Bind, &self.simulation, time);
+            self.selection.make_bound(&mut self.simulation);
+        } else if delete {
+            self.edits.record(Change::Delete, &self.simulation, time);
+            let selection = &self.selection;
+            self.history.tracked.retain(|id| !selection.contains(*id));
+            self.selection.delete(&mut self.simulation);
+        }
+    }
+
+    /// Picks out planet `id` after it was clicked, adding it to the planets
+    /// already picked out or taking it away again if shift is held.
+    fn click_select(&mut self, id: i32, shift: bool) {
+        if shift {
+            self.selection.toggle(id);
+        } else {
+            self.selection = Selection::single(id);
+        }
+    }
+
+    /// The collapsible panel of how trails are kept and drawn, returning the
+    /// responses of everything in it.
+    fn trails_ui(&mut self, ui: &mut egui::Ui, over_popup: &mut bool) -> Vec<egui::Response> {
+        let simulation = &mut self.simulation;
+        let relative = &mut self.relative_trails;
+        let panel = egui::CollapsingHeader::new("trails")
+            .default_open(false)
+            .show(ui, |ui| {
+                let mut responses = trail_style_ui(ui, &mut simulation.trail, over_popup);
+                responses.extend([
+                    ui.add(
+                        egui::Slider::new(&mut simulation.min_trail_update, 0.1..=2.0)
+                            .text("point spacing"),
+                    ),
+                    ui.checkbox(relative, "relative to followed frame"),
+                ]);
+                responses
+            });
+        let mut responses = vec![panel.header_response];
+        responses.extend(panel.body_response);
+        responses.extend(panel.body_returned.into_iter().flatten());
+        responses
+    }
+
+    /// The collapsible panel with the settings of the pattern generator,
+    /// returning the responses of everything in it.
+    fn generator_ui(&mut self, ui: &mut egui::Ui, over_popup: &mut bool) -> Vec<egui::Response> {
+        let generator = &mut self.generator;
+        let spawning = &mut self.spawning;
+        let panel = egui::CollapsingHeader::new("generator")
+            .default_open(false)
+            .show(ui, |ui| {
+                let responses = vec![
+                    ui.checkbox(spawning, "click to spawn"),
+                    choice(
+                        ui,
+                        "pattern",
+                        &mut generator.pattern,
+                        &Pattern::ALL,
+                        Pattern::name,
+                        over_popup,
+                    ),
+                    ui.add(egui::Slider::new(&mut generator.count, 1..=500).text("count")),
+                    ui.add(
+                        egui::Slider::new(&mut generator.inner_radius, 0.0..=1000.0)
+                            .text("inner radius"),
+                    ),
+                    ui.add(
+                        egui::Slider::new(&mut generator.outer_radius, 0.0..=1000.0)
+                            .text("outer radius"),
+                    ),
+                    choice(
+                        ui,
+                        "masses",
+                        &mut generator.masses,
+                        &MassDistribution::ALL,
+                        MassDistribution::name,
+                        over_popup,
+                    ),
+                    ui.add(
+                        egui::Slider::new(&mut generator.min_mass, 0.01..=100.0)
+                            .logarithmic(true)
+                            .text("min mass"),
+                    ),
+                    ui.add(
+                        egui::Slider::new(&mut generator.max_mass, 0.01..=100.0)
+                            .logarithmic(true)
+                            .text("max mass"),
+                    ),
+                    ui.add(egui::Slider::new(&mut generator.size, 0.5..=20.0).text("size")),
+                    ui.add(
+                        egui::Slider::new(&mut generator.eccentricity, 0.0..=0.9)
+                            .text("eccentricity"),
+                    ),
+                    ui.add(
+                        egui::Slider::new(&mut generator.retrograde, 0.0..=1.0)
+                            .text("retrograde fraction"),
+                    ),
+                ];
+                generator.max_mass = generator.max_mass.max(generator.min_mass);
+                responses
+            });
+        let mut responses = vec![panel.header_response];
+        responses.extend(panel.body_response);
+        responses.extend(panel.body_returned.into_iter().flatten());
+        responses
+    }
+
+    /// Spawns the generator's pattern around `pos`, or around the planet
+    /// there if there is one, with everything moving along at `frame_vel`.
+    fn spawn_pattern(&mut self, pos: egui::Pos2, frame_vel: egui::Vec2) {
+        let (center, center_vel) = self
+            .simulation
+            .bodies()
+            .iter()
+            .find(|p| p.pos.distance(pos) <= p.size)
+            .map_or((pos, frame_vel), |p| (p.pos, p.vel));
+        self.generator.spawn(
+            &mut self.simulation,
+            center,
+            center_vel,
+            &mut rand::thread_rng(),
+        );
+    }
+
+    /// The collapsible panel for saving the scene to a file and loading it
+    /// back, returning the responses of everything in it.
+    fn scene_ui(&mut self, ui: &mut egui::Ui) -> Vec<egui::Response> {
+        let panel = egui::CollapsingHeader::new("scene")
+            .default_open(false)
+            .show(ui, |ui| {
+                let mut responses = Vec::new();
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    responses.push(ui.text_edit_singleline(&mut self.scene_path));
+                    ui.horizontal(|ui| {
+                        let save = ui.button("save");
+                        let load = ui.button("load");
+                        if save.clicked() {
+                            self.scene_status =
+                                Some(match self.capture_scene().save(&self.scene_path) {
+                                    Ok(()) => format!("saved to {}", self.scene_path),
+                                    Err(err) => format!("couldn't save: {}", err),
+                                });
+                        }
+                        if load.clicked() {
+                            self.scene_status = Some(match Scene::load(&self.scene_path) {
+                                Ok(scene) => {
+                                    self.edits.record(
+                                        Change::Load,
+                                        &self.simulation,
+                                        ui.input().time,
+                                    );
+                                    self.load_scene(&scene);
+                                    format!("loaded {}", self.scene_path)
+                                }
+                                Err(err) => format!("couldn't load: {}", err),
+                            });
+                        }
+                        responses.push(save);
+                        responses.push(load);
+                    });
+                }
+                #[cfg(target_arch = "wasm32")]
+                {
+                    let copy = ui.button("copy link");
+                    if copy.clicked() {
+                        let link = set_url_fragment(&self.capture_scene().to_link());
+                        self.scene_status = Some(match link {
+                            Some(link) => {
+                                ui.output().copied_text = link;
+                                "link copied".to_owned()
+                            }
+                            None => "couldn't update the address bar".to_owned(),
+                        });
+                    }
+                    responses.push(copy);
+                }
+                if let Some(status) = &self.scene_status {
+                    responses.push(ui.label(status));
+                }
+                responses
+            });
+        let mut responses = vec![panel.header_response];
+        responses.extend(panel.body_response);
+        responses.extend(panel.body_returned.into_iter().flatten());
+        responses
+    }
+
+    fn capture_scene(&self) -> Scene {
+        let view = View {
+            follow: self.follow.clone(),
+            center: Some([self.camera.center.x, self.camera.center.y]),
+            zoom: self.camera.zoom,
+        };
+        Scene::capture(&self.simulation, view)
+    }
+
+    /// Replaces the simulation with `scene` and forgets everything that was
+    /// measured about the old one.
+    fn load_scene(&mut self, scene: &Scene) {
+        let view = scene.restore(&mut self.simulation);
+        self.selection = match view.follow {
+            Follow::Body(id) => Selection::single(id),
+            _ => Selection::default(),
+        };
+        self.follow = view.follow;
+        match view.center {
+            Some([x, y]) => {
+                self.camera = Camera {
+                    center: egui::pos2(x, y),
+                    zoom: view.zoom,
+                    angle: 0.0,
+                }
+            }
+            None => self.fit_requested = true,
+        }
+        self.stepper.reset();
+        self.events.clear();
+        self.baseline = None;
+        self.history.clear();
+        self.history.tracked.clear();
+    }
+
+    /// Lets go of the planet being dragged, if any, running again if the
+    /// grab paused the simulation.
+    fn drop_grab(&mut self) {
+        if let Some(grab) = self.grab.take() {
+            self.paused &= !grab.resume;
+        }
+    }
+
+    /// Takes back the last change to the planets.
+    fn undo(&mut self) {
+        if self.edits.undo(&mut self.simulation).is_some() {
+            self.drop_grab();
+            self.stepper.reset();
+        }
+    }
+
+    /// Makes the last change that was taken back again.
+    fn redo(&mut self) {
+        if self.edits.redo(&mut self.simulation).is_some() {
+            self.drop_grab();
+            self.stepper.reset();
+        }
+    }
+
+    /// The ends of the velocity arrow of planet `id` on screen, relative to
+    /// the frame the camera follows. The tip is the handle for re-aiming the
+    /// planet, and is kept clear of it so it can always be grabbed.
+    fn velocity_arrow(
+        &self,
+        camera: &Camera,
+        screen: egui::Rect,
+        id: i32,
+        alpha: f32,
+    ) -> Option<(egui::Pos2, egui::Pos2)> {
+        let p = self.simulation.body(id)?;
+        let pos = p.interpolated_pos(alpha);
+        let frame = self.follow.frame(&self.simulation, alpha);
+        let vel =
+            camera.to_screen_vec(p.vel - frame.map_or(egui::Vec2::ZERO, |f| f.velocity_at(pos)));
+        let base = camera.to_screen(screen, pos);
+        let min_length = p.size * camera.zoom + 2.0 * HANDLE_RADIUS;
+        let tip = if vel.length() >= min_length {
+            base + vel
+        } else if vel.length() > 0.0 {
+            base + vel.normalized() * min_length
+        } else {
+            base + egui::vec2(min_length, 0.0)
+        };
+        Some((base, tip))
+    }
+
+    /// What pressing at the screen position `pos` picks up: the velocity
+    /// handle of the primary selected planet, or else the planet under the
+    /// pointer.
+    fn grab_at(
+        &self,
+        camera: &Camera,
+        screen: egui::Rect,
+        pos: egui::Pos2,
+        alpha: f32,
+    ) -> Option<Grab> {
+        let resume = !self.paused;
+        let primary = self.selection.primary().unwrap_or(-1);
+        if let Some((_, tip)) = self.velocity_arrow(camera, screen, primary, alpha) {
+            if tip.distance(pos) <= HANDLE_RADIUS + 2.0 {
+                return Some(Grab {
+                    id: primary,
+                    handle: Handle::Velocity,
+                    resume,
+                    moved: false,
+                });
+            }
+        }
+        let world_pos = camera.to_world(screen, pos);
+        self.simulation
+            .bodies()
+            .iter()
+            .rev()
+            .find(|p| {
+                p.interpolated_pos(alpha).distance(world_pos) <= p.size.max(3.0 / camera.zoom)
+            })
+            .map(|p| Grab {
+                id: p.id,
+                handle: Handle::Body,
+                resume,
+                moved: false,
+            })
+    }
+
+    /// Draws where every planet has been, as seen from the followed frame if
+    /// [`Self::relative_trails`] is on.
+    fn draw_trails(&self, painter: &egui::Painter, screen: egui::Rect, camera: Camera, alpha: f32) {
+        let now = self.simulation.time();
+        let frame = self
+            .follow
+            .frame(&self.simulation, alpha)
+            .filter(|_| self.relative_trails);
+        let mut points = Vec::new();
+        for p in self.simulation.bodies() {
+            let style = p.trail_style.unwrap_or(self.simulation.trail);
+            if !style.visible {
+                continue;
+            }
+            points.clear();
+            points.extend(p.trail.points().filter_map(|(time, pos)| {
+                // Put the point where it was relative to the frame back then.
+                let pos = match frame {
+                    Some(now) => now.to_world(self.frames.at(time)?.to_local(pos)),
+                    None => pos,
+                };
+                Some((time, camera.to_screen(screen, pos)))
+            }));
+            for (behind, w) in points.windows(2).rev().enumerate() {
+                let freshness = style.freshness(behind, w[1].0, now);
+                painter.line_segment([w[0].1, w[1].1], style.stroke(freshness, p.color));
+            }
         }
     }
+
+    /// Draws the continuous prediction of where the planets in
+    /// [`Self::predicting`] are going.
+    fn draw_paths(&self, painter: &egui::Painter, screen: egui::Rect, camera: Camera, alpha: f32) {
+        // The paths are already relative to the frame the camera follows.
+        let view = if self.follow.frame(&self.simulation, alpha).is_some() {
+            Camera {
+                center: egui::Pos2::ZERO,
+                angle: 0.0,
+                ..camera
+            }
+        } else {
+            camera
+        };
+        for id in self.prediction.ids() {
+            if self.predicting == PredictionScope::Selected && !self.selection.contains(id) {
+                continue;
+            }
+            let color = self
+                .simulation
+                .body(id)
+                .map_or(egui::Color32::GRAY, |p| p.color);
+            let path = self
+                .prediction
+                .path(id)
+                .map(|pos| view.to_screen(screen, pos))
+                .collect();
+            painter.add(egui::Shape::line(
+                path,
+                egui::Stroke::new(1.0, color.linear_multiply(0.5)),
+            ));
+        }
+    }
+
+    /// Draws the osculating orbit of every selected planet around the planet
+    /// pulling on it the most, with its periapsis and apoapsis marked.
+    fn draw_orbits(&self, painter: &egui::Painter, screen: egui::Rect, camera: Camera, alpha: f32) {
+        // Open orbits only need to go a little past the edge of the screen.
+        let reach = screen.size().length() / camera.zoom;
+        for id in self.selection.ids() {
+            let Some(orbit) = Orbit::around_dominant(&self.simulation, *id) else {
+                continue;
+            };
+            let (Some(planet), Some(attractor)) = (
+                self.simulation.body(*id),
+                self.simulation.body(orbit.attractor),
+            ) else {
+                continue;
+            };
+            let center = attractor.interpolated_pos(alpha);
+            let color = planet.color.linear_multiply(0.6);
+            let path = orbit
+                .path(center, reach)
+                .into_iter()
+                .map(|pos| camera.to_screen(screen, pos))
+                .collect();
+            painter.add(egui::Shape::line(path, egui::Stroke::new(1.0, color)));
+
+            let (periapsis, apoapsis) = orbit.apsides();
+            let apsides = [("Pe", Some(periapsis)), ("Ap", apoapsis)];
+            for (label, offset) in apsides {
+                if let Some(offset) = offset {
+                    let pos = camera.to_screen(screen, center + offset);
+                    painter.circle_filled(pos, 3.0, color);
+                    painter.text(
+                        pos + egui::vec2(4.0, -4.0),
+                        egui::Align2::LEFT_BOTTOM,
+                        label,
+                        egui::TextStyle::Small,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draws where the planets in `preview` are headed over the next
+    /// [`Self::preview_length`] steps, with planet `highlight` standing out.
+    fn draw_prediction(
+        &self,
+        painter: &egui::Painter,
+        screen: egui::Rect,
+        camera: Camera,
+        mut preview: Simulation,
+        highlight: i32,
+    ) {
+        let mut stepper = FixedTimestep::new(u32::MAX);
+        // Draw in the frame the camera follows, as it will be then.
+        let view_of = |preview: &Simulation| {
+            let mut view = camera;
+            if let Some(frame) = self.follow.frame(preview, 1.0) {
+                view.track(&frame);
+            }
+            view
+        };
+        let mut watch = Watch::new(&preview, highlight);
+        // Where the encounters are on screen, as the camera will be then.
+        let mut marks = Vec::new();
+        let mut mark = |watch: &Watch, view: Camera| {
+            let encounters = &watch.encounters()[marks.len()..];
+            marks.extend(encounters.iter().map(|e| {
+                (
+                    *e,
+                    view.to_screen(screen, e.pos),
+                    e.other.map(|(_, pos)| view.to_screen(screen, pos)),
+                )
+            }));
+        };
+        // Reuse the same two buffers for every step.
+        let mut last_points = Vec::new();
+        let mut new_points = Vec::new();
+        for step in 0..self.preview_length {
+            let view = view_of(&preview);
+            mark(&watch, view);
+            new_points.clear();
+            new_points.extend(
+                preview
+                    .bodies()
+                    .iter()
+                    .map(|e| view.to_screen(screen, e.pos)),
+            );
+            if step > 0 {
+                for ((old, pos), e) in last_points.iter().zip(&new_points).zip(preview.bodies()) {
+                    let mut color: egui::color::Hsva = e.color.into();
+                    if e.id != highlight {
+                        color.s /= 2.0;
+                    }
+                    painter.line_segment([*old, *pos], egui::Stroke::new(2.0, color))
+                }
+            }
+            std::mem::swap(&mut last_points, &mut new_points);
+            stepper.advance(&mut preview, PREVIEW_INTERVAL);
+            let events = preview.drain_events().collect::<Vec<_>>();
+            watch.observe(&preview, &events);
+        }
+        mark(&watch, view_of(&preview));
+
+        for (encounter, pos, other) in marks {
+            let (color, text) = match encounter.kind {
+                EncounterKind::Approach => (
+                    egui::Color32::YELLOW,
+                    format!(
+                        "{} {:.0} in {:.1} s",
+                        encounter.kind.name(),
+                        encounter.distance,
+                        encounter.time
+                    ),
+                ),
+                EncounterKind::Collision => (
+                    egui::Color32::RED,
+                    format!("{} in {:.1} s", encounter.kind.name(), encounter.time),
+                ),
+                EncounterKind::Escape => (
+                    egui::Color32::LIGHT_BLUE,
+                    format!("{} in {:.1} s", encounter.kind.name(), encounter.time),
+                ),
+            };
+            if let Some(other) = other {
+                painter.line_segment([pos, other], egui::Stroke::new(1.0, color));
+            }
+            painter.circle_stroke(pos, 4.0, egui::Stroke::new(1.5, color));
+            painter.text(
+                pos + egui::vec2(6.0, -6.0),
+                egui::Align2::LEFT_BOTTOM,
+                text,
+                egui::TextStyle::Small,
+                color,
+            );
+        }
+    }
+
+    /// The velocity a planet released at the world position `pos` starts with.
+    ///
+    /// Normally it is aimed by dragging back from `pos` towards `mouse_pos`, but
+    /// holding shift instead estimates the velocity of a circular orbit around
+    /// whatever is pulling on it.
+    fn launch_velocity(&self, pos: egui::Pos2, mouse_pos: egui::Pos2, orbit: bool) -> egui::Vec2 {
+        if !orbit {
+            return (pos - mouse_pos) * 6.0;
+        }
+        let grav = self.simulation.acceleration_at(pos);
+        let offset = grav.normalized().rot90() * 25.0;
+        let grav_slope = grav.y / grav.x;
+        let grav_offset = self.simulation.acceleration_at(pos + offset);
+        let grav_offset_slope = grav_offset.y / grav_offset.x;
+        // gm1m2/r^2 = m1v^2/r = gravm1
+        let c = pos.y - pos.x * grav_slope;
+        let d = pos.y + offset.y - (pos.x + offset.x) * grav_offset_slope;
+        let x = (d - c) / (grav_slope - grav_offset_slope);
+        let y = x * grav_slope + c;
+        let grav_pos = egui::Pos2::new(x, y);
+        let r = (pos - grav_pos).length();
+        let angle = grav.rot90().angle();
+        (r * grav.length()).sqrt() * egui::Vec2::angled(angle)
+    }
 }
 
 impl epi::App for App {
@@ -62,12 +1274,25 @@ impl epi::App for App {
         _frame: &mut epi::Frame<'_>,
         _storage: Option<&dyn epi::Storage>,
     ) {
+        // A scene from a link takes precedence over the stored state.
+        let startup_scene = self.startup_scene.take();
+        let scene_status = self.scene_status.take();
+
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         #[cfg(feature = "persistence")]
         if let Some(storage) = _storage {
-            *self = epi::get_value(storage, epi::APP_KEY).unwrap_or_default()
+            *self = epi::get_value(storage, epi::APP_KEY).unwrap_or_default();
+            // The last step isn't stored, so start drawing from where they are.
+            for p in self.simulation.bodies_mut() {
+                p.prev_pos = p.pos;
+            }
+        }
+
+        if let Some(scene) = startup_scene {
+            self.load_scene(&scene);
         }
+        self.scene_status = scene_status;
     }
 
     /// Called by the frame work to save state before shutdown.
@@ -85,62 +1310,119 @@ impl epi::App for App {
         // let dt = ctx.input().unstable_dt.min(1.0 / 60.0);
         let dt = ctx.input().predicted_dt;
 
+        let screen = ctx.input().screen_rect();
+        if self.fit_requested {
+            self.camera.fit(screen, self.simulation.bodies());
+            self.fit_requested = false;
+        }
+        let camera = self.camera;
+
         let pointer = &ctx.input().pointer;
-        let old_selected = self.selected;
-        if let Some(mouse_pos) = pointer.interact_pos() {
-            let mut offset_pos = egui::Vec2::ZERO;
-            let mut offset_vel = egui::Vec2::ZERO;
-            if self.selected >= 0 {
-                self.particles.iter().for_each(&mut |p: &Planet| {
-                    if p.id == self.selected {
-                        offset_pos = p.pos.to_vec2() - ctx.available_rect().size() / 2.0;
-                        offset_vel = p.vel;
+        let shift = ctx.input().modifiers.shift;
+        self.selection.retain_existing(&self.simulation);
+        let old_selection = self.selection.clone();
+        let old_follow = self.follow.clone();
+        // New planets start out at rest in the frame the camera follows.
+        let frame = self.follow.frame(&self.simulation, 1.0);
+        let frame_vel = |pos| frame.map_or(egui::Vec2::ZERO, |f| f.velocity_at(pos));
+        // Dragging an existing planet moves it, along with the rest of the
+        // selection if it is part of it, and dragging the handle on the
+        // velocity arrow re-aims every selected planet.
+        // Letting go of a grab or a rubber band isn't also a click.
+        let mut handled_release = false;
+        if let Some(grab) = self.grab {
+            if pointer.any_down() {
+                let delta = camera.to_world_vec(pointer.delta());
+                if delta != egui::Vec2::ZERO && !grab.moved {
+                    self.edits
+                        .record(Change::Drag(grab.id), &self.simulation, ctx.input().time);
+                    self.grab = Some(Grab {
+                        moved: true,
+                        ..grab
+                    });
+                }
+                let group = if self.selection.contains(grab.id) {
+                    self.selection.clone()
+                } else {
+                    Selection::single(grab.id)
+                };
+                match grab.handle {
+                    Handle::Body => group.translate(&mut self.simulation, delta),
+                    Handle::Velocity => group.add_velocity(&mut self.simulation, delta),
+                }
+            } else {
+                self.drop_grab();
+                handled_release = true;
+                // Letting go of a planet without moving it is a click.
+                if grab.handle == Handle::Body {
+                    if !grab.moved {
+                        self.click_select(grab.id, shift);
+                    } else if !self.selection.contains(grab.id) {
+                        self.selection = Selection::single(grab.id);
                     }
-                });
+                }
             }
-            if pointer.any_released() {
-                self.selected = -1;
-                self.particles.iter().for_each(&mut |p: &Planet| {
-                    let pos = p.pos - offset_pos;
-                    // println!("{:?}", p.pos.distance(i));
-                    if pos.distance(mouse_pos) <= p.size {
-                        self.selected = p.id;
+        }
+        // Dragging with the right button selects everything in the rubber
+        // band, or adds it to the selection if shift is held.
+        if let Some(start) = self.band {
+            if !pointer.any_down() {
+                let band = egui::Rect::from_two_pos(start, pointer.interact_pos().unwrap_or(start));
+                if !shift {
+                    self.selection.clear();
+                }
+                for p in self.simulation.bodies() {
+                    if band.contains(camera.to_screen(screen, p.pos)) {
+                        self.selection.insert(p.id);
                     }
-                });
+                }
+                self.band = None;
+                handled_release = true;
             }
-            if self.selected < 0 {
+        }
+        let mut hit = None;
+        if let Some(mouse_pos) = pointer.interact_pos() {
+            let world_pos = camera.to_world(screen, mouse_pos);
+            if pointer.any_released()
+                && on_canvas(ctx, mouse_pos)
+                && !self.spawning
+                && !self.panning
+                && !handled_release
+            {
+                // Keep tiny planets clickable when zoomed out.
+                hit = self
+                    .simulation
+                    .bodies()
+                    .iter()
+                    .rev()
+                    .find(|p| p.pos.distance(world_pos) <= p.size.max(3.0 / camera.zoom))
+                    .map(|p| p.id);
+                if let Some(id) = hit {
+                    self.click_select(id, shift);
+                }
+            }
+            if self.spawning {
                 if let Some(pos) = self.creating {
                     if pointer.any_released() {
-                        let vel: egui::Vec2 = if ctx.input().modifiers.shift {
-                            let grav = Planet::get_force(pos + offset_pos, -1, &self.particles)
-                                * dt
-                                * self.gravity.powf(2.0);
-                            let offset = grav.normalized().rot90() * 25.0;
-                            let grav_slope = grav.y / grav.x;
-                            let grav_offset =
-                                Planet::get_force(pos + offset_pos + offset, -1, &self.particles)
-                                    * dt
-                                    * self.gravity.powf(2.0);
-                            let grav_offset_slope = grav_offset.y / grav_offset.x;
-                            // gm1m2/r^2 = m1v^2/r = gravm1
-                            let c = pos.y - pos.x * grav_slope;
-                            let d = pos.y + offset.y - (pos.x + offset.x) * grav_offset_slope;
-                            let x = (d - c) / (grav_slope - grav_offset_slope);
-                            let y = x * grav_slope + c;
-                            let grav_pos = egui::Pos2::new(x, y);
-                            let r = (pos - grav_pos).length();
-                            let angle = grav.rot90().angle();
-                            let vel = (r * grav.length()).sqrt() * egui::Vec2::angled(angle);
-                            vel
-                        } else {
-                            (pos - mouse_pos) / 10.0
-                        };
-                        self.particles.push(Planet::new(
-                            pos + offset_pos,
-                            vel + offset_vel,
+                        let pos = camera.to_world(screen, pos);
+                        self.edits
+                            .record(Change::Spawn, &self.simulation, ctx.input().time);
+                        self.spawn_pattern(pos, frame_vel(pos));
+                    }
+                }
+            } else if hit.is_none() {
+                if let Some(pos) = self.creating {
+                    if pointer.any_released() {
+                        let pos = camera.to_world(screen, pos);
+                        let vel = self.launch_velocity(pos, world_pos, shift);
+                        self.edits
+                            .record(Change::Create, &self.simulation, ctx.input().time);
+                        self.simulation.add_body(Planet::new(
+                            pos,
+                            vel + frame_vel(pos),
                             self.mass,
                             self.size,
-                            self.last_id,
+                            -1,
                             egui::color::Hsva::new(
                                 rand::random::<f32>(),
                                 1.0,
@@ -148,92 +1430,348 @@ impl epi::App for App {
                                 1.0,
                             ),
                         ));
-                        self.last_id += 1;
-                        self.selected = old_selected;
                     }
                 }
             }
         }
 
-        // a = g*m/(d^2)
-        let zoom_dt = ctx.input().scroll_delta.y;
-        if zoom_dt != 0.0 {
-            self.mass += zoom_dt / 20.0;
-            self.mass = self.mass.max(1.0);
+        // Dragging with the middle button pans, which stops following.
+        if pointer.middle_down() {
+            self.panning = true;
+            self.camera.pan(pointer.delta());
+            self.follow = Follow::Free;
+        } else if !pointer.any_down() {
+            self.panning = false;
+        }
+        // The wheel and pinching zoom about the pointer, or about the middle
+        // of the frame if the camera is following something.
+        if let Some(hover) = pointer.hover_pos().filter(|pos| on_canvas(ctx, *pos)) {
+            let zoom = ctx.input().zoom_delta() * (ctx.input().scroll_delta.y / 200.0).exp();
+            if zoom != 1.0 {
+                self.camera.zoom_about(screen, hover, zoom);
+            }
         }
 
-        if ctx.input().key_pressed(egui::Key::Space) {
-            self.paused = !self.paused;
+        // Text fields have their own use for these keys.
+        if !ctx.wants_keyboard_input() {
+            if ctx.input().key_pressed(egui::Key::Space) {
+                self.paused = !self.paused;
+            }
+            if ctx.input().modifiers.command && ctx.input().key_pressed(egui::Key::Z) {
+                if ctx.input().modifiers.shift {
+                    self.redo();
+                } else {
+                    self.undo();
+                }
+            }
         }
 
-        let mut old = self.particles.clone();
-        if !self.paused {
-            self.particles
-                .iter_mut()
-                .for_each(|p| p.update(&old, self.min_trail_update, self.gravity, dt));
+        let alpha = if self.paused {
+            self.stepper.reset();
+            1.0
+        } else {
+            self.stepper
+                .advance(&mut self.simulation, ctx.input().unstable_dt)
+        };
+        // Measuring energy is O(n²), so only keep history while it's on screen.
+        if self.show_plots {
+            self.history.record(&self.simulation);
+        }
+        let now = ctx.input().time;
+        for event in self.simulation.drain_events() {
+            if let SimulationEvent::Merged {
+                survivor, absorbed, ..
+            } = event
+            {
+                self.selection.replace(absorbed, &[survivor]);
+                self.follow.replace(absorbed, &[survivor]);
+            }
+            if let SimulationEvent::Fragmented {
+                parents, fragments, ..
+            } = &event
+            {
+                for parent in parents {
+                    self.selection.replace(*parent, fragments);
+                    self.follow.replace(*parent, fragments);
+                }
+            }
+            // Planets resting on each other bounce every step, which would
+            // drown out everything else in the list.
+            if matches!(event, SimulationEvent::Bounced { .. }) {
+                continue;
+            }
+            self.events.push((now, event));
+        }
+        self.events.retain(|(time, _)| now - time < EVENT_LIFETIME);
+        // Hold the view still while dragging, so what's dragged stays under
+        // the pointer even if the camera is following it.
+        if let Some(frame) = self
+            .follow
+            .frame(&self.simulation, alpha)
+            .filter(|_| self.grab.is_none())
+        {
+            self.camera.track(&frame);
+        }
+        let camera = self.camera;
+        match self.follow.frame(&self.simulation, 1.0) {
+            Some(frame) => {
+                self.frames
+                    .record(self.simulation.time(), &self.follow, frame);
+                let start = self
+                    .simulation
+                    .bodies()
+                    .iter()
+                    .filter_map(|p| p.trail.start())
+                    .fold(f64::INFINITY, f64::min);
+                self.frames.forget_before(start);
+            }
+            None => self.frames.clear(),
+        }
+        if self.predicting == PredictionScope::Off {
+            self.prediction = Prediction::default();
+        } else if self.grab.is_none() {
+            let n = self.simulation.bodies().len().max(1);
+            let budget = (PREDICTION_BUDGET / (n * n)).clamp(8, 256) as u32;
+            self.prediction.update(
+                &self.simulation,
+                &self.follow,
+                self.prediction_horizon,
+                budget,
+            );
         }
         egui::CentralPanel::default().show(ctx, |ui| {
             let mut responces = vec![
-                ui.add(egui::Slider::new(&mut self.gravity, 0.0..=100.0).text("gravity")),
-                ui.add(egui::Slider::new(&mut self.mass, 1.0..=100.0).text("mass")),
-                ui.add(egui::Slider::new(&mut self.size, 1.0..=100.0).text("size")),
                 ui.add(
-                    egui::Slider::new(&mut self.min_trail_update, 0.1..=2.0).text("trial length"),
+                    egui::Slider::new(&mut self.simulation.gravity, 0.0..=500.0).text("gravity"),
                 ),
+                ui.add(
+                    egui::Slider::new(&mut self.simulation.softening, 0.0..=50.0).text("softening"),
+                ),
+                ui.add(egui::Slider::new(&mut self.mass, 1.0..=100.0).text("mass")),
+                ui.add(egui::Slider::new(&mut self.size, 1.0..=100.0).text("size")),
                 ui.add(
                     egui::Slider::new(&mut self.preview_length, 100..=2000).text("preview length"),
                 ),
-                ui.checkbox(&mut self.force_fields, "force arrows"),
             ];
+            let mut over_popup = false;
+            responces.push(choice(
+                ui,
+                "predict paths",
+                &mut self.predicting,
+                &PredictionScope::ALL,
+                PredictionScope::name,
+                &mut over_popup,
+            ));
+            if self.predicting != PredictionScope::Off {
+                responces.push(
+                    ui.add(
+                        egui::Slider::new(&mut self.prediction_horizon, 1.0..=60.0)
+                            .text("seconds ahead"),
+                    ),
+                );
+            }
+            responces.extend([
+                ui.checkbox(&mut self.force_fields, "force arrows"),
+                ui.checkbox(&mut self.show_orbits, "orbits of selected"),
+            ]);
+            let mut rate = 1.0 / self.simulation.timestep;
+            responces.push(
+                ui.add(
+                    egui::Slider::new(&mut rate, 30.0..=1000.0)
+                        .logarithmic(true)
+                        .text("physics rate (Hz)"),
+                ),
+            );
+            self.simulation.timestep = 1.0 / rate;
+            responces.push(ui.add(
+                egui::Slider::new(&mut self.stepper.max_substeps, 1..=64).text("max substeps"),
+            ));
+            responces.push(choice(
+                ui,
+                "integrator",
+                &mut self.simulation.integrator,
+                &IntegratorKind::ALL,
+                IntegratorKind::name,
+                &mut over_popup,
+            ));
+            responces.push(choice(
+                ui,
+                "force solver",
+                &mut self.simulation.solver,
+                &ForceSolver::ALL,
+                ForceSolver::name,
+                &mut over_popup,
+            ));
+            if self.simulation.solver == ForceSolver::BarnesHut {
+                responces.push(
+                    ui.add(egui::Slider::new(&mut self.simulation.theta, 0.0..=1.5).text("θ")),
+                );
+            }
+            #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+            {
+                responces.push(ui.checkbox(&mut self.simulation.parallel, "parallel forces"));
+                if self.simulation.parallel {
+                    responces
+                        .push(ui.checkbox(&mut self.simulation.deterministic, "deterministic"));
+                }
+            }
+            responces.push(choice(
+                ui,
+                "collisions",
+                &mut self.simulation.collisions,
+                &CollisionMode::ALL,
+                CollisionMode::name,
+                &mut over_popup,
+            ));
+            if self.simulation.collisions == CollisionMode::Merge {
+                responces.push(ui.checkbox(&mut self.simulation.fragmentation, "fragmentation"));
+            }
+            let fragmenting =
+                self.simulation.collisions == CollisionMode::Merge && self.simulation.fragmentation;
+            if fragmenting {
+                responces.push(
+                    ui.add(
+                        egui::Slider::new(&mut self.simulation.fragment_energy, 100.0..=1e6)
+                            .logarithmic(true)
+                            .text("shatter energy"),
+                    ),
+                );
+                responces.push(
+                    ui.add(
+                        egui::Slider::new(&mut self.simulation.fragment_count, 2..=16)
+                            .text("fragments"),
+                    ),
+                );
+            }
+            if self.simulation.collisions == CollisionMode::Bounce || fragmenting {
+                responces.push(
+                    ui.add(
+                        egui::Slider::new(&mut self.simulation.restitution, 0.0..=1.0)
+                            .text("restitution"),
+                    ),
+                );
+            }
+            responces.push(ui.checkbox(&mut self.simulation.adaptive, "adaptive timestep"));
+            if self.simulation.adaptive {
+                responces.push(
+                    ui.add(
+                        egui::Slider::new(&mut self.simulation.tolerance, 0.001..=1.0)
+                            .logarithmic(true)
+                            .text("error tolerance"),
+                    ),
+                );
+                responces.push(ui.label(format!("substeps: {}", self.simulation.substeps())));
+            }
+            responces.extend(self.diagnostics_ui(ui));
+            responces.extend(self.frame_ui(ui, &mut over_popup));
+            responces.extend(self.trails_ui(ui, &mut over_popup));
+            responces.extend(self.generator_ui(ui, &mut over_popup));
+            responces.extend(self.scene_ui(ui));
+            responces.push(ui.checkbox(&mut self.show_plots, "plots"));
+            ui.horizontal(|ui| {
+                responces.push(choice(
+                    ui,
+                    "preset",
+                    &mut self.preset,
+                    &Preset::ALL,
+                    Preset::name,
+                    &mut over_popup,
+                ));
+                let load = ui.button("load");
+                if load.clicked() {
+                    let scene = self.preset.scene(self.camera.center);
+                    self.edits.record(Change::Preset, &self.simulation, now);
+                    self.load_scene(&scene);
+                }
+                responces.push(load);
+            });
             if self.force_fields {
                 responces.push(
                     ui.add(egui::Slider::new(&mut self.arrow_size, 3.0..=60.0).text("arrow size")),
                 )
             }
-            if responces.iter().any(|r| r.dragged() || r.hovered()) {
-                self.selected = old_selected;
+            let fit = ui.button("fit all");
+            if fit.clicked() {
+                self.follow = Follow::Free;
+                self.camera.angle = 0.0;
+                self.camera.fit(screen, self.simulation.bodies());
+            }
+            responces.push(fit);
+            if over_popup || responces.iter().any(|r| r.dragged() || r.hovered()) {
+                self.selection = old_selection.clone();
                 self.creating = None;
             } else {
-                self.creating = pointer.press_origin();
-            }
-            if ui.button("reset").clicked() {
-                self.particles = Vec::new();
-            }
-            let mut selected_pos = egui::Vec2::ZERO;
-            if self.selected >= 0 {
-                self.particles.iter_mut().for_each(|p| {
-                    if p.id == self.selected {
-                        selected_pos = p.pos.to_vec2() - ctx.available_rect().size() / 2.0
+                if pointer.any_pressed() && pointer.primary_down() && !self.spawning {
+                    if let Some(origin) = pointer.press_origin().filter(|o| on_canvas(ctx, *o)) {
+                        self.grab = self.grab_at(&camera, screen, origin, alpha);
+                        if self.grab.is_some() {
+                            // Edit a still picture, not a moving target.
+                            self.paused = true;
+                        }
                     }
+                }
+                if pointer.any_pressed() && pointer.secondary_down() {
+                    self.band = pointer.press_origin().filter(|o| on_canvas(ctx, *o));
+                }
+                self.creating = pointer.press_origin().filter(|origin| {
+                    on_canvas(ctx, *origin)
+                        && !self.panning
+                        && self.grab.is_none()
+                        && self.band.is_none()
                 });
             }
+            ui.horizontal(|ui| {
+                if ui.button("reset").clicked() {
+                    self.edits.record(Change::Reset, &self.simulation, now);
+                    self.simulation.clear();
+                }
+                let undo = self.edits.next_undo();
+                let undo_button = ui.add_enabled(undo.is_some(), egui::Button::new("undo"));
+                if undo_button
+                    .on_hover_text(format!("undo {} (ctrl+z)", undo.map_or("", Change::name)))
+                    .clicked()
+                {
+                    self.undo();
+                }
+                let redo = self.edits.next_redo();
+                let redo_button = ui.add_enabled(redo.is_some(), egui::Button::new("redo"));
+                if redo_button
+                    .on_hover_text(format!(
+                        "redo {} (ctrl+shift+z)",
+                        redo.map_or("", Change::name)
+                    ))
+                    .clicked()
+                {
+                    self.redo();
+                }
+            });
+            for (_, event) in &self.events {
+                ui.colored_label(egui::Color32::YELLOW, event.to_string());
+            }
+            let gravity = self.simulation.gravity;
             let painter = ui.painter();
             if self.force_fields {
-                let size = ctx.available_rect().size();
+                let field = self.simulation.field();
+                let size = screen.size();
                 let mut key_points = Vec::new();
                 let key_points_dist = 4.0;
                 for x in 0..((size.x / self.arrow_size) / key_points_dist).ceil() as usize + 1 {
                     for y in 0..((size.y / self.arrow_size) / key_points_dist).ceil() as usize + 1 {
-                        let pos = (egui::Vec2::new(x as f32, y as f32)
-                            * self.arrow_size
-                            * key_points_dist)
-                            + selected_pos;
-                        let mut min_dist_sq = 10000.0;
-                        let vel = old
+                        let pos = screen.min
+                            + egui::Vec2::new(x as f32, y as f32)
+                                * self.arrow_size
+                                * key_points_dist;
+                        let min_dist_sq = self
+                            .simulation
+                            .bodies()
                             .iter()
-                            .map(|d| {
-                                // (d.pos - p.pos).normalized() * dt * (self.gravity * p.mass * d.mass)
-                                let dist_sq = d.pos.distance_sq(pos.to_pos2());
-                                if dist_sq < min_dist_sq {
-                                    min_dist_sq = dist_sq
-                                }
-                                (d.pos - pos).to_vec2().normalized()
-                                    * dt
-                                    * (self.gravity.powf(2.0) * d.mass)
-                                    / dist_sq
-                            })
-                            .fold(egui::Vec2::ZERO, |v1, v2| v1 + v2);
-                        let color = (vel.length() * 10000.0 / (self.gravity.powf(2.0))).min(1.0);
+                            .map(|d| camera.to_screen(screen, d.pos).distance_sq(pos))
+                            .fold(10000.0, f32::min);
+                        let vel = camera
+                            .to_screen_vec(field.acceleration(camera.to_world(screen, pos)))
+                            / camera.zoom
+                            * dt;
+                        let color = (vel.length() * 10000.0 / (gravity.powf(2.0))).min(1.0);
                         if y == 0 {
                             key_points.push(Vec::new());
                         }
@@ -261,8 +1799,8 @@ impl epi::App for App {
                 let mut idx = 0;
                 for x in 0..x_size {
                     for y in 0..y_size {
-                        let mut pos =
-                            (egui::Vec2::new(x as f32, y as f32) * self.arrow_size) + selected_pos;
+                        let pos =
+                            screen.min + egui::Vec2::new(x as f32, y as f32) * self.arrow_size;
                         let left = (x as f32 / key_points_dist).floor() as usize;
                         let right = (x as f32 / key_points_dist).ceil() as usize;
                         let x_frac = (x as f32 / key_points_dist).fract();
@@ -281,15 +1819,10 @@ impl epi::App for App {
                         .unwrap();
                         if highest_rendering_level > 0 {
                             let vel = if highest_rendering_level == 2 {
-                                old.iter()
-                                    .map(|d| {
-                                        // (d.pos - p.pos).normalized() * dt * (self.gravity * p.mass * d.mass)
-                                        (d.pos - pos).to_vec2().normalized()
-                                            * dt
-                                            * (self.gravity.powf(2.0) * d.mass)
-                                            / d.pos.distance_sq(pos.to_pos2())
-                                    })
-                                    .fold(egui::Vec2::ZERO, |v1, v2| v1 + v2)
+                                camera
+                                    .to_screen_vec(field.acceleration(camera.to_world(screen, pos)))
+                                    / camera.zoom
+                                    * dt
                             } else {
                                 y_frac
                                     * (key_points[right][bottom].0 * x_frac
@@ -304,10 +1837,9 @@ impl epi::App for App {
                                 + (1.0 - y_frac)
                                     * (key_points[right][top].1 * x_frac
                                         + key_points[left][top].1 * (1.0 - x_frac));
-                            pos -= selected_pos;
                             if arrows.is_empty() {
                                 painter.arrow(
-                                    pos.to_pos2(),
+                                    pos,
                                     vel.normalized() * self.arrow_size,
                                     egui::Stroke::new(
                                         1.0,
@@ -316,10 +1848,7 @@ impl epi::App for App {
                                 );
                             } else {
                                 arrows[idx] = egui::Shape::LineSegment {
-                                    points: [
-                                        pos.to_pos2(),
-                                        (pos + vel.normalized() * self.arrow_size).to_pos2(),
-                                    ],
+                                    points: [pos, pos + vel.normalized() * self.arrow_size],
                                     stroke: egui::Stroke::new(
                                         1.0,
                                         egui::color::Hsva::new(color, 1.0, 1.0, color),
@@ -328,10 +1857,9 @@ impl epi::App for App {
                                 idx += 1;
                             }
                         } else {
-                            pos -= selected_pos;
                             if arrows.is_empty() {
                                 painter.arrow(
-                                    pos.to_pos2(),
+                                    pos,
                                     key_points[right][bottom].0.normalized() * self.arrow_size,
                                     egui::Stroke::new(
                                         1.0,
@@ -346,10 +1874,9 @@ impl epi::App for App {
                             } else {
                                 arrows[idx] = egui::Shape::LineSegment {
                                     points: [
-                                        pos.to_pos2(),
-                                        (pos + key_points[right][bottom].0.normalized()
-                                            * self.arrow_size)
-                                            .to_pos2(),
+                                        pos,
+                                        pos + key_points[right][bottom].0.normalized()
+                                            * self.arrow_size,
                                     ],
                                     stroke: egui::Stroke::new(
                                         1.0,
@@ -368,91 +1895,119 @@ impl epi::App for App {
                 }
                 painter.extend(arrows);
             }
-            self.particles.iter_mut().for_each(|p| {
-                painter.circle_filled(p.pos - selected_pos, p.size, p.color);
-                p.trail.windows(2).for_each(|w| {
-                    painter.line_segment(
-                        [w[0] - selected_pos, w[1] - selected_pos],
-                        egui::Stroke::new(2.0, p.color),
-                    )
-                })
+            self.simulation.bodies().iter().for_each(|p| {
+                let pos = camera.to_screen(screen, p.interpolated_pos(alpha));
+                painter.circle_filled(pos, p.size * camera.zoom, p.color);
+                if self.selection.contains(p.id) {
+                    painter.circle_stroke(
+                        pos,
+                        p.size * camera.zoom + 3.0,
+                        egui::Stroke::new(1.0, egui::Color32::WHITE),
+                    );
+                }
             });
-            if let Some(pos) = self.creating {
-                painter.circle_filled(pos, self.size, egui::Color32::GREEN);
+            self.draw_trails(painter, screen, camera, alpha);
+            for (time, event) in &self.events {
+                let age = ((now - time) / EVENT_LIFETIME) as f32;
+                if let SimulationEvent::Merged { survivor, pos, .. } = event {
+                    let size = self.simulation.body(*survivor).map_or(0.0, |p| p.size);
+                    painter.circle_stroke(
+                        camera.to_screen(screen, *pos),
+                        size * camera.zoom + age * 40.0,
+                        egui::Stroke::new(2.0, egui::Color32::YELLOW.linear_multiply(1.0 - age)),
+                    );
+                }
+                if let SimulationEvent::Fragmented { pos, .. } = event {
+                    painter.circle_stroke(
+                        camera.to_screen(screen, *pos),
+                        age * 80.0,
+                        egui::Stroke::new(3.0, egui::Color32::RED.linear_multiply(1.0 - age)),
+                    );
+                }
+            }
+            if let Some(pos) = self.creating.filter(|_| self.spawning) {
+                let stroke = egui::Stroke::new(1.0, egui::Color32::GREEN);
+                painter.circle_stroke(pos, self.generator.inner_radius * camera.zoom, stroke);
+                painter.circle_stroke(pos, self.generator.outer_radius * camera.zoom, stroke);
+            } else if let Some(pos) = self.creating {
+                painter.circle_filled(pos, self.size * camera.zoom, egui::Color32::GREEN);
                 if let Some(mouse_pos) = pointer.interact_pos() {
-                    let mut offset_pos = egui::Vec2::ZERO;
-                    let mut offset_vel = egui::Vec2::ZERO;
-                    if self.selected >= 0 {
-                        old.iter().for_each(&mut |p: &Planet| {
-                            if p.id == self.selected {
-                                offset_pos = p.pos.to_vec2() - ctx.available_rect().size() / 2.0;
-                                offset_vel = p.vel;
-                            }
-                        });
-                    }
-                    let vel: egui::Vec2 = if ctx.input().modifiers.shift {
-                        let grav = Planet::get_force(pos + offset_pos, -1, &self.particles)
-                            * dt
-                            * self.gravity.powf(2.0);
-                        let offset = grav.normalized().rot90() * 25.0;
-                        let grav_slope = grav.y / grav.x;
-                        let grav_offset =
-                            Planet::get_force(pos + offset_pos + offset, -1, &self.particles)
-                                * dt
-                                * self.gravity.powf(2.0);
-                        let grav_offset_slope = grav_offset.y / grav_offset.x;
-                        // gm1m2/r^2 = m1v^2/r = gravm1
-                        let c = pos.y - pos.x * grav_slope;
-                        let d = pos.y + offset.y - (pos.x + offset.x) * grav_offset_slope;
-                        let x = (d - c) / (grav_slope - grav_offset_slope);
-                        let y = x * grav_slope + c;
-                        let grav_pos = egui::Pos2::new(x, y);
-                        let r = (pos - grav_pos).length();
-                        let angle = grav.rot90().angle();
-                        let vel = (r * grav.length()).sqrt() * egui::Vec2::angled(angle);
-                        vel
-                    } else {
-                        (pos - mouse_pos) / 10.0
-                    };
-                    painter.arrow(pos, vel, egui::Stroke::new(1.0, egui::Color32::GREEN));
-                    old.push(Planet::new(
-                        pos + offset_pos,
-                        vel + offset_vel,
+                    let world_pos = camera.to_world(screen, pos);
+                    let vel = self.launch_velocity(
+                        world_pos,
+                        camera.to_world(screen, mouse_pos),
+                        ctx.input().modifiers.shift,
+                    );
+                    painter.arrow(
+                        pos,
+                        camera.to_screen_vec(vel),
+                        egui::Stroke::new(1.0, egui::Color32::GREEN),
+                    );
+                    let mut preview = self.simulation.clone();
+                    let id = preview.add_body(Planet::new(
+                        world_pos,
+                        vel + frame_vel(world_pos),
                         self.mass,
                         self.size,
-                        self.last_id,
+                        -1,
                         egui::Color32::GREEN,
                     ));
-                    let mut last_points: Option<Vec<_>> = None;
-                    for _ in 0..self.preview_length {
-                        let temp = old.clone();
-                        let mut offset_pos = egui::Vec2::ZERO;
-                        if self.selected >= 0 {
-                            old.iter().for_each(&mut |p: &Planet| {
-                                if p.id == self.selected {
-                                    offset_pos =
-                                        p.pos.to_vec2() - ctx.available_rect().size() / 2.0;
-                                }
-                            });
-                        }
-                        let new_points = old.iter().map(|e| (e.pos - offset_pos, e.color));
-                        if let Some(ops) = last_points {
-                            for (i, ps) in ops.iter().zip(new_points.clone()).enumerate() {
-                                let (pos, color) = ps.1;
-                                let mut color: egui::color::Hsva = color.into();
-                                if i != temp.len() - 1 {
-                                    color.s /= 2.0;
-                                }
-                                painter.line_segment([*ps.0, pos], egui::Stroke::new(2.0, color))
-                            }
-                        }
-                        last_points = Some(new_points.map(|e| e.0).collect());
-                        old.iter_mut()
-                            .for_each(|p| p.update(&temp, self.min_trail_update, self.gravity, dt));
-                    }
+                    self.draw_prediction(painter, screen, camera, preview, id);
                 }
+            } else if let Some(grab) = self.grab {
+                self.draw_prediction(painter, screen, camera, self.simulation.clone(), grab.id);
+            }
+            // The previews of edits in progress show the future instead.
+            if self.predicting != PredictionScope::Off
+                && self.creating.is_none()
+                && self.grab.is_none()
+            {
+                self.draw_paths(painter, screen, camera, alpha);
+            }
+            if self.show_orbits {
+                self.draw_orbits(painter, screen, camera, alpha);
+            }
+            if !self.spawning && self.creating.is_none() {
+                let primary = self.selection.primary().unwrap_or(-1);
+                if let Some((base, tip)) = self.velocity_arrow(&camera, screen, primary, alpha) {
+                    let stroke = egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE);
+                    painter.arrow(base, tip - base, stroke);
+                    painter.circle_stroke(tip, HANDLE_RADIUS, stroke);
+                }
+            }
+            if let Some((start, end)) = self.band.zip(pointer.interact_pos()) {
+                painter.rect_stroke(
+                    egui::Rect::from_two_pos(start, end),
+                    0.0,
+                    egui::Stroke::new(1.0, egui::Color32::LIGHT_BLUE),
+                );
             }
             egui::warn_if_debug_build(ui);
         });
+
+        if self.selection != old_selection && !self.selection.is_empty() {
+            self.inspecting = true;
+        }
+        self.inspector_ui(ctx);
+        self.selection_ui(ctx);
+
+        // Picking a planet follows it, unless the camera is busy following
+        // something else.
+        if self.selection != old_selection
+            && self.follow == old_follow
+            && matches!(self.follow, Follow::Free | Follow::Body(_))
+        {
+            self.follow = match self.selection.primary() {
+                Some(id) => Follow::Body(id),
+                None => Follow::Free,
+            };
+        }
+
+        let history = &mut self.history;
+        let simulation = &self.simulation;
+        egui::Window::new("plots")
+            .open(&mut self.show_plots)
+            .default_width(300.0)
+            .show(ctx, |ui| history.ui(ui, simulation));
     }
 }
@@ -0,0 +1,181 @@
+use crate::gravity::{finite, softened_pull};
+use eframe::egui;
+
+/// Below this size cells stop splitting, so bodies sitting on top of each
+/// other share a leaf instead of recursing forever.
+const MIN_HALF_SIZE: f32 = 1e-3;
+
+/// Cells this deep stop splitting too, for bodies so far apart that halving
+/// the root takes longer than that to get down to [`MIN_HALF_SIZE`].
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone)]
+struct Node {
+    center: egui::Pos2,
+    half: f32,
+    mass: f32,
+    /// Center of mass, kept up to date as bodies are added so that far apart
+    /// bodies can't overflow a sum of mass times position.
+    com: egui::Vec2,
+    /// Index of the first of the four children, or 0 for a leaf.
+    children: usize,
+    /// The only body in this leaf, if there is exactly one.
+    body: Option<usize>,
+    count: usize,
+}
+
+impl Node {
+    fn new(center: egui::Pos2, half: f32) -> Self {
+        Self {
+            center,
+            half,
+            mass: 0.0,
+            com: egui::Vec2::ZERO,
+            children: 0,
+            body: None,
+            count: 0,
+        }
+    }
+
+    fn quadrant(&self, pos: egui::Pos2) -> usize {
+        (pos.x >= self.center.x) as usize + 2 * (pos.y >= self.center.y) as usize
+    }
+
+    fn add(&mut self, pos: egui::Pos2, mass: f32) {
+        self.mass += mass;
+        if self.mass > 0.0 {
+            let weight = mass / self.mass;
+            self.com = self.com * (1.0 - weight) + pos.to_vec2() * weight;
+        }
+        self.count += 1;
+    }
+}
+
+/// A Barnes-Hut quadtree, which approximates the pull of far away groups of
+/// bodies by their center of mass to get forces in O(n log n).
+#[derive(Debug, Clone)]
+pub struct QuadTree {
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    /// A tree of the bodies at `pos`, leaving out any that blew up.
+    pub fn new(pos: &[egui::Pos2], masses: &[f32]) -> Self {
+        let included = |body: &usize| finite(pos[*body], masses[*body]);
+        let (min, max) = (0..pos.len()).filter(included).map(|body| pos[body]).fold(
+            (
+                egui::pos2(f32::INFINITY, f32::INFINITY),
+                egui::pos2(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            ),
+            |(min, max), p| (min.min(p), max.max(p)),
+        );
+        let (center, half) = if min.x > max.x {
+            (egui::Pos2::ZERO, 1.0)
+        } else {
+            // Halve before subtracting, which can't overflow.
+            let half_size = max.to_vec2() / 2.0 - min.to_vec2() / 2.0;
+            (
+                min + half_size,
+                (half_size.x.max(half_size.y).max(MIN_HALF_SIZE) * 1.01).min(f32::MAX),
+            )
+        };
+        let mut tree = Self {
+            nodes: vec![Node::new(center, half)],
+        };
+        for body in (0..pos.len()).filter(included) {
+            tree.insert(body, pos, masses);
+        }
+        tree
+    }
+
+    fn insert(&mut self, body: usize, pos: &[egui::Pos2], masses: &[f32]) {
+        let mut idx = 0;
+        for depth in 0.. {
+            let node = &mut self.nodes[idx];
+            node.add(pos[body], masses[body]);
+            if node.children != 0 {
+                idx = node.children + node.quadrant(pos[body]);
+                continue;
+            }
+            if node.count == 1 {
+                node.body = Some(body);
+                return;
+            }
+            if node.half <= MIN_HALF_SIZE || depth >= MAX_DEPTH {
+                node.body = None;
+                return;
+            }
+
+            // Split the leaf and move the body that was living here down a level.
+            let (center, half) = (node.center, node.half / 2.0);
+            let existing = node.body.take();
+            let first = self.nodes.len();
+            self.nodes[idx].children = first;
+            for q in 0..4 {
+                let offset = egui::vec2(
+                    if q & 1 == 0 { -half } else { half },
+                    if q & 2 == 0 { -half } else { half },
+                );
+                self.nodes.push(Node::new(center + offset, half));
+            }
+            if let Some(other) = existing {
+                let child = first + self.nodes[idx].quadrant(pos[other]);
+                self.nodes[child].add(pos[other], masses[other]);
+                self.nodes[child].body = Some(other);
+            }
+            idx = first + self.nodes[idx].quadrant(pos[body]);
+        }
+    }
+
+    /// The pull per unit of gravity at `pos`, ignoring body `exclude`.
+    ///
+    /// Cells that look smaller than `theta` radians from `pos` are treated as
+    /// a single mass, so `theta == 0.0` is exact and larger values are faster.
+    pub fn pull(
+        &self,
+        pos: egui::Pos2,
+        exclude: Option<usize>,
+        theta: f32,
+        eps_sq: f32,
+    ) -> egui::Vec2 {
+        let mut total = egui::Vec2::ZERO;
+        let mut stack = vec![0];
+        while let Some(idx) = stack.pop() {
+            let node = &self.nodes[idx];
+            if node.count == 0 || (node.count == 1 && node.body == exclude) {
+                continue;
+            }
+            // Leaves holding a pile of coincident bodies may include `exclude`
+            // itself, but it sits at the pile's center so it adds no pull.
+            let offset = node.com.to_pos2() - pos;
+            if node.children == 0 || 2.0 * node.half < theta * offset.length() {
+                total += softened_pull(offset, node.mass, eps_sq);
+            } else {
+                stack.extend(node.children..node.children + 4);
+            }
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_out_bodies_that_blew_up() {
+        let pos = [
+            egui::pos2(f32::INFINITY, 0.0),
+            egui::pos2(0.0, f32::NAN),
+            egui::pos2(-3e38, 0.0),
+            egui::pos2(3e38, 1.0),
+            egui::pos2(3e38, 2.0),
+            egui::pos2(1.0, 1.0),
+        ];
+        let masses = [1.0; 6];
+        let tree = QuadTree::new(&pos, &masses);
+        assert_eq!(tree.nodes[0].count, 4);
+        let pull = tree.pull(egui::pos2(0.0, 0.0), None, 0.5, 1.0);
+        assert!(pull.x.is_finite() && pull.y.is_finite());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::diagnostics::cross;
+use crate::planet::Planet;
+use crate::simulation::Simulation;
+use eframe::egui;
+
+const MIN_ZOOM: f32 = 1e-3;
+const MAX_ZOOM: f32 = 1e3;
+
+/// Where the view is looking, for mapping between the world the simulation
+/// happens in and the points on the screen it gets drawn at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The point of the world shown in the middle of the screen.
+    pub center: egui::Pos2,
+    /// Screen points per unit of world distance.
+    pub zoom: f32,
+    /// The direction in the world, in radians, that points right on screen.
+    pub angle: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            center: egui::Pos2::ZERO,
+            zoom: 1.0,
+            angle: 0.0,
+        }
+    }
+}
+
+/// `v` turned by `angle` radians.
+fn rotate(v: egui::Vec2, angle: f32) -> egui::Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    egui::vec2(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+}
+
+impl Camera {
+    /// Where the world position `pos` shows up on `screen`.
+    pub fn to_screen(&self, screen: egui::Rect, pos: egui::Pos2) -> egui::Pos2 {
+        screen.center() + self.to_screen_vec(pos - self.center)
+    }
+
+    /// The world position shown at `pos` on `screen`.
+    pub fn to_world(&self, screen: egui::Rect, pos: egui::Pos2) -> egui::Pos2 {
+        self.center + self.to_world_vec(pos - screen.center())
+    }
+
+    /// How a world displacement or velocity looks on screen.
+    pub fn to_screen_vec(&self, v: egui::Vec2) -> egui::Vec2 {
+        rotate(v, -self.angle) * self.zoom
+    }
+
+    /// The world displacement that looks like `v` on screen.
+    pub fn to_world_vec(&self, v: egui::Vec2) -> egui::Vec2 {
+        rotate(v, self.angle) / self.zoom
+    }
+
+    /// Zooms in by `factor`, keeping the world under the screen position
+    /// `anchor` where it is.
+    pub fn zoom_about(&mut self, screen: egui::Rect, anchor: egui::Pos2, factor: f32) {
+        let fixed = self.to_world(screen, anchor);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.center = fixed - self.to_world_vec(anchor - screen.center());
+    }
+
+    /// Drags the world along by `delta` screen points.
+    pub fn pan(&mut self, delta: egui::Vec2) {
+        self.center -= self.to_world_vec(delta);
+    }
+
+    /// Centers and zooms so every body in `bodies` is on `screen`, with a
+    /// bit of room around them.
+    pub fn fit(&mut self, screen: egui::Rect, bodies: &[Planet]) {
+        if bodies.is_empty() {
+            *self = Self::default();
+            return;
+        }
+        // Measure the bounds the way they'll look on screen.
+        let bounds = bodies.iter().fold(egui::Rect::NOTHING, |bounds, p| {
+            bounds.union(egui::Rect::from_center_size(
+                rotate(p.pos.to_vec2(), -self.angle).to_pos2(),
+                egui::Vec2::splat(2.0 * p.size),
+            ))
+        });
+        self.center = rotate(bounds.center().to_vec2(), self.angle).to_pos2();
+        let size = bounds.size().max(egui::Vec2::splat(1.0));
+        self.zoom = (0.9 * (screen.width() / size.x).min(screen.height() / size.y))
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Moves the camera along with `frame`.
+    pub fn track(&mut self, frame: &Frame) {
+        self.center = frame.origin;
+        self.angle = frame.angle;
+    }
+}
+
+/// What the camera moves along with.
+#[derive(Debug, Clone, PartialEq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Follow {
+    /// The camera stays wherever it is put.
+    #[default]
+    Free,
+    /// Centered on a single planet.
+    Body(i32),
+    /// Centered on the center of mass of all planets.
+    Barycenter,
+    /// Centered on the center of mass of some of the planets.
+    Subset(Vec<i32>),
+    /// Centered on the center of mass of two planets and turning with them,
+    /// so the first always appears straight left of the second.
+    CoRotating(i32, i32),
+}
+
+/// A moving, possibly rotating, reference frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub origin: egui::Pos2,
+    pub vel: egui::Vec2,
+    /// The direction in the world, in radians, of the frame's x axis.
+    pub angle: f32,
+    /// How fast `angle` changes, in radians per second.
+    pub spin: f32,
+}
+
+impl Frame {
+    /// The velocity of something at `pos` that is standing still in the frame.
+    pub fn velocity_at(&self, pos: egui::Pos2) -> egui::Vec2 {
+        let offset = pos - self.origin;
+        self.vel + egui::vec2(-offset.y, offset.x) * self.spin
+    }
+
+    /// Where the world position `pos` is as seen from the frame, with the
+    /// origin at zero and the x axis pointing along `angle`.
+    pub fn to_local(self, pos: egui::Pos2) -> egui::Pos2 {
+        rotate(pos - self.origin, -self.angle).to_pos2()
+    }
+
+    /// The world position of `local`, the inverse of [`Self::to_local`].
+    pub fn to_world(self, local: egui::Pos2) -> egui::Pos2 {
+        self.origin + rotate(local.to_vec2(), self.angle)
+    }
+}
+
+impl Follow {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Free => "free",
+            Self::Body(_) => "planet",
+            Self::Barycenter => "barycenter",
+            Self::Subset(_) => "subset barycenter",
+            Self::CoRotating(..) => "co-rotating",
+        }
+    }
+
+    /// Where the frame is in `simulation`, using positions `alpha` of the
+    /// way through the last step. None if there is nothing to follow.
+    pub fn frame(&self, simulation: &Simulation, alpha: f32) -> Option<Frame> {
+        match self {
+            Self::Free => None,
+            Self::Body(id) => barycenter(simulation.body(*id).into_iter(), alpha),
+            Self::Barycenter => barycenter(simulation.bodies().iter(), alpha),
+            Self::Subset(ids) => barycenter(
+                simulation.bodies().iter().filter(|p| ids.contains(&p.id)),
+                alpha,
+            ),
+            Self::CoRotating(first, second) => {
+                let a = simulation.body(*first)?;
+                let b = simulation.body(*second)?;
+                let offset = b.interpolated_pos(alpha) - a.interpolated_pos(alpha);
+                let dist_sq = offset.length_sq();
+                if first == second || dist_sq <= 0.0 {
+                    return None;
+                }
+                Some(Frame {
+                    angle: offset.angle(),
+                    spin: cross(offset, b.vel - a.vel) / dist_sq,
+                    ..barycenter([a, b].into_iter(), alpha)?
+                })
+            }
+        }
+    }
+
+    /// Follows what became of planet `old` after it merged or shattered into
+    /// `new`. Single planets are swapped for the first of `new`.
+    pub fn replace(&mut self, old: i32, new: &[i32]) {
+        let Some(&first) = new.first() else {
+            return;
+        };
+        match self {
+            Self::Body(id) if *id == old => *id = first,
+            Self::Subset(ids) if ids.contains(&old) => {
+                ids.retain(|id| *id != old && !new.contains(id));
+                ids.extend(new);
+            }
+            Self::CoRotating(a, b) => {
+                for id in [a, b] {
+                    if *id == old {
+                        *id = first;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The non-rotating frame of the center of mass of `bodies`.
+fn barycenter<'a>(bodies: impl Iterator<Item = &'a Planet>, alpha: f32) -> Option<Frame> {
+    let (mass, pos, vel) = bodies.fold(
+        (0.0, egui::Vec2::ZERO, egui::Vec2::ZERO),
+        |(mass, pos, vel), p| {
+            (
+                mass + p.mass,
+                pos + p.interpolated_pos(alpha).to_vec2() * p.mass,
+                vel + p.vel * p.mass,
+            )
+        },
+    );
+    (mass > 0.0).then(|| Frame {
+        origin: (pos / mass).to_pos2(),
+        vel: vel / mass,
+        angle: 0.0,
+        spin: 0.0,
+    })
+}
@@ -0,0 +1,272 @@
+use crate::planet::Planet;
+use crate::simulation::SimulationEvent;
+use eframe::egui;
+use rand::prelude::*;
+
+/// Fragments lighter than this aren't worth creating, so impacts that would
+/// produce them make fewer fragments or just merge.
+const MIN_FRAGMENT_MASS: f32 = 1.0;
+
+/// What happens when two planets touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum CollisionMode {
+    /// They stick together into one planet.
+    Merge,
+    /// They bounce off each other, losing energy according to the restitution.
+    Bounce,
+    /// They pass through each other.
+    #[default]
+    Ignore,
+}
+
+impl CollisionMode {
+    pub const ALL: [Self; 3] = [Self::Merge, Self::Bounce, Self::Ignore];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::Bounce => "bounce",
+            Self::Ignore => "ignore",
+        }
+    }
+}
+
+/// When merging planets shatter instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fragmentation {
+    /// Impacts with more kinetic energy per unit of total mass than this break
+    /// up, measured in the center of mass frame.
+    pub energy: f32,
+    /// How many pieces a shattered pair breaks into.
+    pub count: u32,
+    /// Fraction of the impact energy the pieces fly apart with.
+    pub retained: f32,
+}
+
+/// Every pair of overlapping planets, as indices into `bodies`.
+///
+/// Sweeps along the x axis so only planets whose extents overlap there get
+/// compared, which is close to linear for spread out scenes.
+pub fn overlapping_pairs(bodies: &[Planet]) -> Vec<(usize, usize)> {
+    let mut order = (0..bodies.len()).collect::<Vec<_>>();
+    order.sort_by(|a, b| {
+        let a = bodies[*a].pos.x - bodies[*a].size;
+        let b = bodies[*b].pos.x - bodies[*b].size;
+        a.total_cmp(&b)
+    });
+    let mut pairs = Vec::new();
+    for (k, &i) in order.iter().enumerate() {
+        let a = &bodies[i];
+        for &j in &order[k + 1..] {
+            let b = &bodies[j];
+            if b.pos.x - b.size > a.pos.x + a.size {
+                break;
+            }
+            if touching(a, b) {
+                pairs.push((i.min(j), i.max(j)));
+            }
+        }
+    }
+    pairs
+}
+
+fn touching(a: &Planet, b: &Planet) -> bool {
+    a.pos.distance_sq(b.pos) < (a.size + b.size).powf(2.0)
+}
+
+/// Resolves every collision among `bodies` according to `mode`, giving any
+/// fragments ids counting up from `next_id`.
+pub fn resolve(
+    bodies: &mut Vec<Planet>,
+    mode: CollisionMode,
+    restitution: f32,
+    fragmentation: Option<Fragmentation>,
+    next_id: &mut i32,
+    events: &mut Vec<SimulationEvent>,
+) {
+    if mode == CollisionMode::Ignore {
+        return;
+    }
+    let pairs = overlapping_pairs(bodies);
+    if pairs.is_empty() {
+        return;
+    }
+    let mut removed = vec![false; bodies.len()];
+    for (i, j) in pairs {
+        if removed[i] || removed[j] || !touching(&bodies[i], &bodies[j]) {
+            continue;
+        }
+        match mode {
+            CollisionMode::Merge => {
+                if let Some(rule) = fragmentation {
+                    if let Some(fragments) = shatter(&bodies[i], &bodies[j], rule, next_id) {
+                        removed[i] = true;
+                        removed[j] = true;
+                        events.push(SimulationEvent::Fragmented {
+                            parents: [bodies[i].id, bodies[j].id],
+                            fragments: fragments.iter().map(|p| p.id).collect(),
+                            pos: bodies[i].pos + (bodies[j].pos - bodies[i].pos) / 2.0,
+                        });
+                        bodies.extend(fragments);
+                        removed.resize(bodies.len(), false);
+                        continue;
+                    }
+                }
+                let (survivor, absorbed) = if bodies[i].mass >= bodies[j].mass {
+                    (i, j)
+                } else {
+                    (j, i)
+                };
+                let other = bodies[absorbed].clone();
+                merge(&mut bodies[survivor], &other);
+                removed[absorbed] = true;
+                events.push(SimulationEvent::Merged {
+                    survivor: bodies[survivor].id,
+                    absorbed: other.id,
+                    pos: bodies[survivor].pos,
+                });
+            }
+            CollisionMode::Bounce => {
+                let (left, right) = bodies.split_at_mut(j);
+                let (a, b) = (&mut left[i], &mut right[0]);
+                bounce(a, b, restitution);
+                events.push(SimulationEvent::Bounced {
+                    ids: [a.id, b.id],
+                    pos: a.pos + (b.pos - a.pos) * (a.size / (a.size + b.size)),
+                });
+            }
+            CollisionMode::Ignore => {}
+        }
+    }
+    let mut idx = 0;
+    bodies.retain(|_| {
+        idx += 1;
+        !removed[idx - 1]
+    });
+}
+
+/// Turns `planet` into the perfectly inelastic combination of itself and
+/// `other`, conserving mass and momentum and keeping the total area.
+pub fn merge(planet: &mut Planet, other: &Planet) {
+    let mass = planet.mass + other.mass;
+    let weight = other.mass / mass;
+    planet.pos = planet.pos + (other.pos - planet.pos) * weight;
+    planet.prev_pos = planet.prev_pos + (other.prev_pos - planet.prev_pos) * weight;
+    planet.vel = (planet.vel * planet.mass + other.vel * other.mass) / mass;
+    planet.size = planet.size.hypot(other.size);
+    planet.color = blend(planet.color, other.color, weight);
+    planet.mass = mass;
+}
+
+/// Breaks `a` and `b` into pieces if they hit hard enough.
+///
+/// The pieces conserve the pair's total mass and momentum, start out around
+/// their common center of mass without touching each other and fly apart
+/// with `rule.retained` of the kinetic energy of the impact.
+pub fn shatter(
+    a: &Planet,
+    b: &Planet,
+    rule: Fragmentation,
+    next_id: &mut i32,
+) -> Option<Vec<Planet>> {
+    let total = a.mass + b.mass;
+    let reduced = a.mass * b.mass / total;
+    let impact = 0.5 * reduced * (a.vel - b.vel).length_sq();
+    let count = rule.count.min((total / MIN_FRAGMENT_MASS) as u32);
+    if impact / total <= rule.energy || count < 2 {
+        return None;
+    }
+
+    // Seeded from the ids so the same impact always breaks the same way.
+    let mut rng = StdRng::seed_from_u64((a.id as u64) << 32 | b.id as u32 as u64);
+    let mut whole = a.clone();
+    merge(&mut whole, b);
+
+    let weights = (0..count)
+        .map(|_| rng.gen_range(0.5..1.5))
+        .collect::<Vec<f32>>();
+    let weight_sum = weights.iter().sum::<f32>();
+    let masses = weights
+        .iter()
+        .map(|w| total * w / weight_sum)
+        .collect::<Vec<_>>();
+    let sizes = masses
+        .iter()
+        .map(|m| whole.size * (m / total).sqrt())
+        .collect::<Vec<_>>();
+
+    // Spread them on a ring wide enough that neighbours don't overlap.
+    let largest = sizes.iter().copied().fold(0.0, f32::max);
+    let ring = 1.5 * largest / (std::f32::consts::PI / count as f32).sin();
+    let spin = rng.gen_range(0.0..std::f32::consts::TAU);
+    let dirs = (0..count)
+        .map(|k| egui::Vec2::angled(spin + std::f32::consts::TAU * k as f32 / count as f32))
+        .collect::<Vec<_>>();
+
+    // Remove the drift that unequal masses would give the center of mass and
+    // the total momentum, then scale the spread to the energy left over.
+    let mean_dir = dirs
+        .iter()
+        .zip(&masses)
+        .fold(egui::Vec2::ZERO, |sum, (d, m)| sum + *d * *m)
+        / total;
+    let spread = dirs.iter().map(|d| *d - mean_dir).collect::<Vec<_>>();
+    let energy_per_speed_sq = spread
+        .iter()
+        .zip(&masses)
+        .map(|(s, m)| 0.5 * m * s.length_sq())
+        .sum::<f32>();
+    let speed = (rule.retained * impact / energy_per_speed_sq).sqrt();
+
+    let base: egui::color::Hsva = whole.color.into();
+    Some(
+        (0..count as usize)
+            .map(|k| {
+                let mut color = base;
+                color.h = (color.h + rng.gen_range(-0.05..0.05)).rem_euclid(1.0);
+                color.v = (color.v * rng.gen_range(0.7..1.3)).clamp(0.1, 1.0);
+                *next_id += 1;
+                Planet::new(
+                    whole.pos + spread[k] * ring,
+                    whole.vel + spread[k] * speed,
+                    masses[k],
+                    sizes[k],
+                    *next_id - 1,
+                    color,
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Mixes `b` into `a` in linear color space, `t` of the way.
+pub fn blend(a: egui::Color32, b: egui::Color32, t: f32) -> egui::Color32 {
+    (egui::Rgba::from(a) * (1.0 - t) + egui::Rgba::from(b) * t).into()
+}
+
+/// Pushes two overlapping planets apart and exchanges momentum along the line
+/// between their centers.
+fn bounce(a: &mut Planet, b: &mut Planet, restitution: f32) {
+    let offset = b.pos - a.pos;
+    let dist = offset.length();
+    let normal = if dist > 0.0 {
+        offset / dist
+    } else {
+        egui::Vec2::X
+    };
+    let inv_a = 1.0 / a.mass;
+    let inv_b = 1.0 / b.mass;
+
+    // Separate them so they don't stay stuck inside each other.
+    let overlap = a.size + b.size - dist;
+    a.pos -= normal * overlap * inv_a / (inv_a + inv_b);
+    b.pos += normal * overlap * inv_b / (inv_a + inv_b);
+
+    let rel_vel = b.vel - a.vel;
+    let approach = rel_vel.x * normal.x + rel_vel.y * normal.y;
+    if approach < 0.0 {
+        let impulse = -(1.0 + restitution) * approach / (inv_a + inv_b);
+        a.vel -= normal * impulse * inv_a;
+        b.vel += normal * impulse * inv_b;
+    }
+}
@@ -0,0 +1,85 @@
+use crate::simulation::Simulation;
+use eframe::egui;
+
+/// Totals that an isolated system should conserve, for judging how well the
+/// integrator is doing.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Diagnostics {
+    pub bodies: usize,
+    pub mass: f32,
+    pub kinetic: f32,
+    /// Potential energy of the softened force law, so it matches the forces.
+    pub potential: f32,
+    pub momentum: egui::Vec2,
+    /// Angular momentum about the center of mass.
+    pub angular_momentum: f32,
+    pub center_of_mass: egui::Pos2,
+}
+
+impl Diagnostics {
+    pub fn measure(simulation: &Simulation) -> Self {
+        let bodies = simulation.bodies();
+        let mass = bodies.iter().map(|p| p.mass).sum::<f32>();
+        if mass <= 0.0 {
+            return Self::default();
+        }
+        let momentum = bodies
+            .iter()
+            .fold(egui::Vec2::ZERO, |sum, p| sum + p.vel * p.mass);
+        let center_of_mass = (bodies
+            .iter()
+            .fold(egui::Vec2::ZERO, |sum, p| sum + p.pos.to_vec2() * p.mass)
+            / mass)
+            .to_pos2();
+        let drift = momentum / mass;
+
+        let kinetic = bodies
+            .iter()
+            .map(|p| 0.5 * p.mass * p.vel.length_sq())
+            .sum::<f32>();
+        let angular_momentum = bodies
+            .iter()
+            .map(|p| p.mass * cross(p.pos - center_of_mass, p.vel - drift))
+            .sum::<f32>();
+
+        let g = simulation.gravity.powf(2.0);
+        let eps_sq = simulation.softening.powf(2.0);
+        let mut potential = 0.0;
+        for (i, a) in bodies.iter().enumerate() {
+            for b in &bodies[i + 1..] {
+                let r = (a.pos.distance_sq(b.pos) + eps_sq).sqrt();
+                if r > 0.0 {
+                    potential -= g * a.mass * b.mass / r;
+                }
+            }
+        }
+
+        Self {
+            bodies: bodies.len(),
+            mass,
+            kinetic,
+            potential,
+            momentum,
+            angular_momentum,
+            center_of_mass,
+        }
+    }
+
+    pub fn energy(&self) -> f32 {
+        self.kinetic + self.potential
+    }
+}
+
+/// The z component of the cross product of `a` and `b`.
+pub fn cross(a: egui::Vec2, b: egui::Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// How far `now` has strayed from `then`, relative to the size of `then`.
+pub fn relative_drift(now: f32, then: f32) -> f32 {
+    if then == 0.0 {
+        now - then
+    } else {
+        (now - then) / then.abs()
+    }
+}
@@ -0,0 +1,249 @@
+use crate::simulation::{Simulation, SimulationEvent};
+use eframe::egui;
+use std::collections::BTreeMap;
+
+/// Closest approaches further apart than this many times the sizes of the two
+/// planets put together aren't worth pointing out.
+const APPROACH_RANGE: f32 = 8.0;
+
+/// What happens to a planet on its way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncounterKind {
+    /// It passes another planet as close as it is going to.
+    Approach,
+    /// It touches another planet.
+    Collision,
+    /// It is moving away fast enough to leave everything else behind.
+    Escape,
+}
+
+impl EncounterKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Approach => "closest",
+            Self::Collision => "impact",
+            Self::Escape => "escapes",
+        }
+    }
+}
+
+/// A moment worth pointing out on the predicted path of a planet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encounter {
+    pub kind: EncounterKind,
+    /// Seconds from the start of the prediction.
+    pub time: f32,
+    /// Where the planet is then.
+    pub pos: egui::Pos2,
+    /// The planet it meets and where that is then, unless it escapes.
+    pub other: Option<(i32, egui::Pos2)>,
+    /// How far apart the centers of the two are then.
+    pub distance: f32,
+}
+
+/// How the planet and another one were moving at the previous step.
+#[derive(Debug, Clone, Copy)]
+struct Pass {
+    pos: egui::Pos2,
+    other_pos: egui::Pos2,
+    approaching: bool,
+}
+
+/// Watches one planet through the steps of a prediction for the
+/// [`Encounter`]s it has along the way.
+#[derive(Debug, Clone)]
+pub struct Watch {
+    id: i32,
+    start: f64,
+    last_time: f64,
+    passes: BTreeMap<i32, Pass>,
+    /// Planets it already collided with, which have nothing more to show.
+    hit: Vec<i32>,
+    escaped: bool,
+    encounters: Vec<Encounter>,
+}
+
+impl Watch {
+    /// Starts watching planet `id` from where `simulation` is now.
+    pub fn new(simulation: &Simulation, id: i32) -> Self {
+        let mut watch = Self {
+            id,
+            start: simulation.time(),
+            last_time: simulation.time(),
+            passes: BTreeMap::new(),
+            hit: Vec::new(),
+            escaped: false,
+            encounters: Vec::new(),
+        };
+        watch.observe(simulation, &[]);
+        watch
+    }
+
+    /// Looks at `simulation` one step further along, with the `events` it
+    /// had on the way there.
+    pub fn observe(&mut self, simulation: &Simulation, events: &[SimulationEvent]) {
+        let time = simulation.time();
+        let elapsed = |fraction: f32| {
+            (self.last_time + (time - self.last_time) * fraction as f64 - self.start) as f32
+        };
+        // Colliding planets merge, shatter or bounce apart before the two
+        // can be seen touching, so go by what the simulation says happened.
+        for event in events {
+            let (other, pos) = match *event {
+                SimulationEvent::Merged {
+                    survivor: a,
+                    absorbed: b,
+                    pos,
+                }
+                | SimulationEvent::Fragmented {
+                    parents: [a, b],
+                    pos,
+                    ..
+                }
+                | SimulationEvent::Bounced { ids: [a, b], pos }
+                    if a == self.id || b == self.id =>
+                {
+                    (if a == self.id { b } else { a }, pos)
+                }
+                _ => continue,
+            };
+            if self.hit.contains(&other) {
+                continue;
+            }
+            self.hit.push(other);
+            self.encounters.push(Encounter {
+                kind: EncounterKind::Collision,
+                time: elapsed(1.0),
+                pos,
+                other: Some((other, pos)),
+                distance: 0.0,
+            });
+        }
+        let Some(planet) = simulation.body(self.id) else {
+            self.last_time = time;
+            return;
+        };
+        for other in simulation.bodies() {
+            if other.id == self.id || self.hit.contains(&other.id) {
+                continue;
+            }
+            let offset = other.pos - planet.pos;
+            let approaching = offset.x * (other.vel.x - planet.vel.x)
+                + offset.y * (other.vel.y - planet.vel.y)
+                < 0.0;
+            let pass = Pass {
+                pos: planet.pos,
+                other_pos: other.pos,
+                approaching,
+            };
+            let Some(last) = self.passes.insert(other.id, pass) else {
+                continue;
+            };
+            // The closest the two came since the last step, as if both went
+            // straight there.
+            let (from, to) = (last.other_pos - last.pos, offset);
+            let along = to - from;
+            let fraction = if along.length_sq() > 0.0 {
+                (-(from.x * along.x + from.y * along.y) / along.length_sq()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let distance = (from + along * fraction).length();
+            let encounter = |kind| Encounter {
+                kind,
+                time: elapsed(fraction),
+                pos: last.pos + (pass.pos - last.pos) * fraction,
+                other: Some((
+                    other.id,
+                    last.other_pos + (pass.other_pos - last.other_pos) * fraction,
+                )),
+                distance,
+            };
+            let reach = planet.size + other.size;
+            if distance <= reach {
+                self.hit.push(other.id);
+                self.encounters.push(encounter(EncounterKind::Collision));
+            } else if last.approaching && !approaching && distance <= reach * APPROACH_RANGE {
+                self.encounters.push(encounter(EncounterKind::Approach));
+            }
+        }
+        // Forget the planets that merged away.
+        self.passes.retain(|id, _| simulation.body(*id).is_some());
+
+        if !self.escaped && escaping(simulation, self.id) {
+            self.escaped = true;
+            self.encounters.push(Encounter {
+                kind: EncounterKind::Escape,
+                time: elapsed(1.0),
+                pos: planet.pos,
+                other: None,
+                distance: 0.0,
+            });
+        }
+        self.last_time = time;
+    }
+
+    /// Everything found so far, in the order it happens.
+    pub fn encounters(&self) -> &[Encounter] {
+        &self.encounters
+    }
+}
+
+/// Whether planet `id` has more than enough energy to get away from all the
+/// others, and is already heading away from them.
+fn escaping(simulation: &Simulation, id: i32) -> bool {
+    let Some(planet) = simulation.body(id) else {
+        return false;
+    };
+    let g = simulation.gravity.powf(2.0);
+    let (mut mass, mut center, mut vel, mut potential) =
+        (0.0, egui::Vec2::ZERO, egui::Vec2::ZERO, 0.0);
+    for p in simulation.bodies().iter().filter(|p| p.id != id) {
+        mass += p.mass;
+        center += p.pos.to_vec2() * p.mass;
+        vel += p.vel * p.mass;
+        potential -=
+            g * p.mass / (p.pos.distance_sq(planet.pos) + simulation.softening.powf(2.0)).sqrt();
+    }
+    if mass <= 0.0 {
+        return false;
+    }
+    let offset = planet.pos - (center / mass).to_pos2();
+    let vel = planet.vel - vel / mass;
+    vel.length_sq() / 2.0 + potential > 0.0 && offset.x * vel.x + offset.y * vel.y > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collision::CollisionMode;
+    use crate::planet::Planet;
+
+    #[test]
+    fn marks_impacts_that_collisions_resolve() {
+        for mode in CollisionMode::ALL {
+            let mut simulation = Simulation::new(0.0, 1.0 / 240.0);
+            simulation.collisions = mode;
+            for (x, vx) in [(-50.0, 100.0), (50.0, -100.0)] {
+                let pos = egui::pos2(x, 0.0);
+                let vel = egui::vec2(vx, 0.0);
+                simulation.add_body(Planet::new(pos, vel, 10.0, 5.0, -1, egui::Color32::WHITE));
+            }
+            let [a, b] = [simulation.bodies()[0].id, simulation.bodies()[1].id];
+            let mut watch = Watch::new(&simulation, a);
+            for _ in 0..240 {
+                simulation.tick();
+                let events = simulation.drain_events().collect::<Vec<_>>();
+                watch.observe(&simulation, &events);
+            }
+            let impacts = watch
+                .encounters()
+                .iter()
+                .filter(|e| e.kind == EncounterKind::Collision)
+                .collect::<Vec<_>>();
+            assert_eq!(impacts.len(), 1, "{}", mode.name());
+            assert_eq!(impacts[0].other.map(|(id, _)| id), Some(b));
+            assert!((impacts[0].time - 0.45).abs() < 0.05, "{}", impacts[0].time);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::planet::Planet;
+use crate::simulation::Simulation;
+use eframe::egui;
+use rand::prelude::*;
+use std::f32::consts::TAU;
+
+/// How the bodies of a [`Generator`] are laid out around its center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Pattern {
+    /// Evenly spaced around the center, at radii spread over the range.
+    #[default]
+    Ring,
+    /// Scattered evenly over the area between the inner and outer radius.
+    Disk,
+    /// Normally distributed around the center, with the outer radius at two
+    /// standard deviations, and nothing closer than the inner radius.
+    Cloud,
+}
+
+impl Pattern {
+    pub const ALL: [Self; 3] = [Self::Ring, Self::Disk, Self::Cloud];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ring => "ring",
+            Self::Disk => "Keplerian disk",
+            Self::Cloud => "Gaussian cloud",
+        }
+    }
+}
+
+/// How the masses of generated bodies are spread between the lightest and
+/// the heaviest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum MassDistribution {
+    #[default]
+    Uniform,
+    /// As many bodies in each factor of mass, so light ones are common.
+    LogUniform,
+}
+
+impl MassDistribution {
+    pub const ALL: [Self; 2] = [Self::Uniform, Self::LogUniform];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Uniform => "uniform",
+            Self::LogUniform => "log-uniform",
+        }
+    }
+
+    fn sample(self, min: f32, max: f32, rng: &mut impl Rng) -> f32 {
+        if min >= max {
+            return min;
+        }
+        match self {
+            Self::Uniform => rng.gen_range(min..=max),
+            Self::LogUniform => rng.gen_range(min.ln()..=max.ln()).exp(),
+        }
+    }
+}
+
+/// Spawns many bodies at once, each on an orbit around a center through the
+/// pull of the bodies already in the simulation.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Generator {
+    pub pattern: Pattern,
+    pub count: u32,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub min_mass: f32,
+    pub max_mass: f32,
+    pub masses: MassDistribution,
+    /// Size of the heaviest bodies; lighter ones are smaller by area.
+    pub size: f32,
+    /// Orbits get an eccentricity anywhere from zero up to this.
+    pub eccentricity: f32,
+    /// Fraction of the bodies that orbit the other way around.
+    pub retrograde: f32,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            pattern: Pattern::default(),
+            count: 50,
+            inner_radius: 80.0,
+            outer_radius: 150.0,
+            min_mass: 0.1,
+            max_mass: 1.0,
+            masses: MassDistribution::default(),
+            size: 2.0,
+            eccentricity: 0.0,
+            retrograde: 0.0,
+        }
+    }
+}
+
+impl Generator {
+    /// Adds the bodies to `simulation` around `center`, which moves at
+    /// `center_vel`, and returns their ids.
+    ///
+    /// Each body gets the velocity of a Keplerian orbit through its position,
+    /// using the inward part of the pull of the bodies that were there before
+    /// as if it came from a point mass at `center`. Where nothing pulls them
+    /// in they just move along with the center.
+    pub fn spawn(
+        &self,
+        simulation: &mut Simulation,
+        center: egui::Pos2,
+        center_vel: egui::Vec2,
+        rng: &mut impl Rng,
+    ) -> Vec<i32> {
+        let (inner, outer) = (
+            self.inner_radius.min(self.outer_radius),
+            self.inner_radius.max(self.outer_radius),
+        );
+        let hue = rng.gen::<f32>();
+        let offsets = (0..self.count)
+            .map(|k| match self.pattern {
+                Pattern::Ring => {
+                    egui::Vec2::angled(TAU * k as f32 / self.count as f32)
+                        * rng.gen_range(inner..=outer)
+                }
+                Pattern::Disk => {
+                    egui::Vec2::angled(rng.gen_range(0.0..TAU))
+                        * rng.gen_range(inner.powf(2.0)..=outer.powf(2.0)).sqrt()
+                }
+                Pattern::Cloud => loop {
+                    // Box-Muller, retried until it falls inside the range.
+                    let r = outer / 2.0 * (-2.0 * (1.0 - rng.gen::<f32>()).ln()).sqrt();
+                    if r >= inner {
+                        break egui::Vec2::angled(rng.gen_range(0.0..TAU)) * r;
+                    }
+                },
+            })
+            .collect::<Vec<_>>();
+
+        // Work out every velocity before adding anything, so the new bodies
+        // don't pull on each other's orbits.
+        let planets = offsets
+            .into_iter()
+            .map(|offset| {
+                let mass = self.masses.sample(self.min_mass, self.max_mass, rng);
+                let size = (self.size * (mass / self.max_mass).sqrt()).max(0.5);
+                let pos = center + offset;
+                let vel = center_vel + self.orbital_velocity(simulation, offset, pos, rng);
+                let color = egui::color::Hsva::new(
+                    (hue + rng.gen_range(-0.05..0.05)).rem_euclid(1.0),
+                    rng.gen_range(0.5..1.0),
+                    rng.gen_range(0.6..1.0),
+                    1.0,
+                );
+                Planet::new(pos, vel, mass, size, -1, color)
+            })
+            .collect::<Vec<_>>();
+        planets
+            .into_iter()
+            .map(|planet| simulation.add_body(planet))
+            .collect()
+    }
+
+    /// The velocity relative to the center of a body at `offset` from it,
+    /// picked from the orbits through that point with an eccentricity up to
+    /// [`Self::eccentricity`].
+    fn orbital_velocity(
+        &self,
+        simulation: &Simulation,
+        offset: egui::Vec2,
+        pos: egui::Pos2,
+        rng: &mut impl Rng,
+    ) -> egui::Vec2 {
+        let r = offset.length();
+        if r <= 0.0 {
+            return egui::Vec2::ZERO;
+        }
+        let out = offset / r;
+        let acc = simulation.acceleration_at(pos);
+        let inward = -(acc.x * out.x + acc.y * out.y);
+        if inward <= 0.0 {
+            return egui::Vec2::ZERO;
+        }
+        // The gravitational parameter of a point mass at the center that
+        // would pull this hard.
+        let mu = inward * r * r;
+        let e = rng.gen_range(0.0..=self.eccentricity.clamp(0.0, 0.99));
+        let anomaly = rng.gen_range(0.0..TAU);
+        let speed = (mu / (r * (1.0 + e * anomaly.cos()))).sqrt();
+        let radial = speed * e * anomaly.sin();
+        let tangential = speed * (1.0 + e * anomaly.cos());
+        let turn = if rng.gen::<f32>() < self.retrograde {
+            -1.0
+        } else {
+            1.0
+        };
+        out * radial + out.rot90() * tangential * turn
+    }
+}
@@ -0,0 +1,233 @@
+use crate::barnes_hut::QuadTree;
+use eframe::egui;
+
+/// How the pull of every body on every other body gets added up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum ForceSolver {
+    /// Exact O(n²) summation over every pair.
+    #[default]
+    Direct,
+    /// O(n log n) approximation with a quadtree.
+    BarnesHut,
+}
+
+impl ForceSolver {
+    pub const ALL: [Self; 2] = [Self::Direct, Self::BarnesHut];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::BarnesHut => "Barnes-Hut",
+        }
+    }
+}
+
+/// The force law and how to evaluate it.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(
+    not(all(feature = "parallel", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub struct Gravity {
+    /// The gravitational constant.
+    pub g: f32,
+    /// Square of the softening length.
+    pub eps_sq: f32,
+    pub solver: ForceSolver,
+    /// Opening angle of the Barnes-Hut solver.
+    pub theta: f32,
+    /// Spread the work over all cores, when built with the `parallel` feature.
+    pub parallel: bool,
+    /// Keep the parallel results bit-identical to the serial ones, at the
+    /// cost of computing every pair twice.
+    pub deterministic: bool,
+}
+
+impl Gravity {
+    /// Fills `acc` with the acceleration of every body.
+    pub fn accelerations(&self, masses: &[f32], pos: &[egui::Pos2], acc: &mut [egui::Vec2]) {
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        if self.parallel {
+            return self.par_accelerations(masses, pos, acc);
+        }
+        match self.solver {
+            ForceSolver::Direct => {
+                for (i, a) in acc.iter_mut().enumerate() {
+                    *a = direct_pull(pos[i], Some(i), masses, pos, self.eps_sq) * self.g;
+                }
+            }
+            ForceSolver::BarnesHut => {
+                let tree = QuadTree::new(pos, masses);
+                for (i, a) in acc.iter_mut().enumerate() {
+                    *a = tree.pull(pos[i], Some(i), self.theta, self.eps_sq) * self.g;
+                }
+            }
+        }
+    }
+
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    fn par_accelerations(&self, masses: &[f32], pos: &[egui::Pos2], acc: &mut [egui::Vec2]) {
+        use rayon::prelude::*;
+
+        match self.solver {
+            // Every body sums up its own pull in the same order as the serial
+            // loop, so only which thread does it changes.
+            ForceSolver::Direct if self.deterministic => {
+                acc.par_iter_mut().enumerate().for_each(|(i, a)| {
+                    *a = direct_pull(pos[i], Some(i), masses, pos, self.eps_sq) * self.g;
+                });
+            }
+            // Visit each pair once and apply the pull to both ends, adding the
+            // per-thread totals in whatever order the threads finish.
+            ForceSolver::Direct => {
+                let n = pos.len();
+                let total = (0..n)
+                    .into_par_iter()
+                    .fold(
+                        || vec![egui::Vec2::ZERO; n],
+                        |mut acc, i| {
+                            if !finite(pos[i], masses[i]) {
+                                return acc;
+                            }
+                            for j in (i + 1)..n {
+                                if !finite(pos[j], masses[j]) {
+                                    continue;
+                                }
+                                let pull = softened_pull(pos[j] - pos[i], 1.0, self.eps_sq);
+                                acc[i] += pull * masses[j];
+                                acc[j] -= pull * masses[i];
+                            }
+                            acc
+                        },
+                    )
+                    .reduce(
+                        || vec![egui::Vec2::ZERO; n],
+                        |mut a, b| {
+                            a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                            a
+                        },
+                    );
+                for (a, t) in acc.iter_mut().zip(total) {
+                    *a = t * self.g;
+                }
+            }
+            // Walking the tree for one body doesn't depend on any other body.
+            ForceSolver::BarnesHut => {
+                let tree = QuadTree::new(pos, masses);
+                acc.par_iter_mut().enumerate().for_each(|(i, a)| {
+                    *a = tree.pull(pos[i], Some(i), self.theta, self.eps_sq) * self.g;
+                });
+            }
+        }
+    }
+
+    /// The field a massless test particle feels, set up once so it can be
+    /// sampled at many points.
+    pub fn field(&self, masses: Vec<f32>, pos: Vec<egui::Pos2>) -> Field {
+        let tree = match self.solver {
+            ForceSolver::Direct => None,
+            ForceSolver::BarnesHut => Some(QuadTree::new(&pos, &masses)),
+        };
+        Field {
+            gravity: *self,
+            masses,
+            pos,
+            tree,
+        }
+    }
+}
+
+/// The gravitational field of a fixed set of bodies.
+pub struct Field {
+    gravity: Gravity,
+    masses: Vec<f32>,
+    pos: Vec<egui::Pos2>,
+    tree: Option<QuadTree>,
+}
+
+impl Field {
+    pub fn acceleration(&self, at: egui::Pos2) -> egui::Vec2 {
+        let pull = match &self.tree {
+            Some(tree) => tree.pull(at, None, self.gravity.theta, self.gravity.eps_sq),
+            None => direct_pull(at, None, &self.masses, &self.pos, self.gravity.eps_sq),
+        };
+        pull * self.gravity.g
+    }
+}
+
+fn direct_pull(
+    at: egui::Pos2,
+    exclude: Option<usize>,
+    masses: &[f32],
+    pos: &[egui::Pos2],
+    eps_sq: f32,
+) -> egui::Vec2 {
+    pos.iter()
+        .zip(masses)
+        .enumerate()
+        .filter(|(j, (p, m))| Some(*j) != exclude && finite(**p, **m))
+        .map(|(_, (p, m))| softened_pull(*p - at, *m, eps_sq))
+        .fold(egui::Vec2::ZERO, |v1, v2| v1 + v2)
+}
+
+/// Whether a body at `pos` of mass `mass` can pull on anything. One that blew
+/// up would turn the pull on every other body into NaN, and take them all
+/// down with it before it gets removed.
+pub fn finite(pos: egui::Pos2, mass: f32) -> bool {
+    pos.x.is_finite() && pos.y.is_finite() && mass.is_finite()
+}
+
+/// The pull per unit of gravity of a mass `mass` that lies `offset` away.
+pub fn softened_pull(offset: egui::Vec2, mass: f32, eps_sq: f32) -> egui::Vec2 {
+    let r_sq = offset.length_sq() + eps_sq;
+    if r_sq > 0.0 {
+        // Divide first, so a far away heavy mass can't overflow to inf / inf.
+        offset * (mass / (r_sq * r_sq.sqrt()))
+    } else {
+        egui::Vec2::ZERO
+    }
+}
+
+#[cfg(all(test, feature = "parallel", not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn deterministic_parallel_matches_serial_bit_for_bit() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let masses = (0..200)
+            .map(|_| rng.gen_range(0.1..50.0))
+            .collect::<Vec<f32>>();
+        let pos = (0..200)
+            .map(|_| egui::pos2(rng.gen_range(-500.0..500.0), rng.gen_range(-500.0..500.0)))
+            .collect::<Vec<_>>();
+        for solver in ForceSolver::ALL {
+            let serial = Gravity {
+                g: 900.0,
+                eps_sq: 1.0,
+                solver,
+                theta: 0.5,
+                parallel: false,
+                deterministic: false,
+            };
+            let parallel = Gravity {
+                parallel: true,
+                deterministic: true,
+                ..serial
+            };
+            let mut expected = vec![egui::Vec2::ZERO; pos.len()];
+            let mut actual = vec![egui::Vec2::ZERO; pos.len()];
+            serial.accelerations(&masses, &pos, &mut expected);
+            parallel.accelerations(&masses, &pos, &mut actual);
+            for (e, a) in expected.iter().zip(&actual) {
+                assert_eq!(
+                    (e.x.to_bits(), e.y.to_bits()),
+                    (a.x.to_bits(), a.y.to_bits()),
+                    "{}",
+                    solver.name()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use eframe::egui;
+
+/// Fills the second slice with the acceleration of every body when the bodies
+/// are at the positions in the first slice.
+pub(crate) type Accelerations<'a> = dyn FnMut(&[egui::Pos2], &mut [egui::Vec2]) + 'a;
+
+/// A numerical method for advancing the bodies' positions and velocities.
+pub(crate) trait Integrator {
+    /// Advances `pos` and `vel` by `dt` seconds.
+    fn integrate(
+        &self,
+        pos: &mut [egui::Pos2],
+        vel: &mut [egui::Vec2],
+        dt: f32,
+        accel: &mut Accelerations<'_>,
+    );
+}
+
+/// First order, moves with the old velocity then kicks with the old acceleration.
+/// Cheap, but gains energy every step so orbits spiral outwards.
+pub(crate) struct ExplicitEuler;
+
+impl Integrator for ExplicitEuler {
+    fn integrate(
+        &self,
+        pos: &mut [egui::Pos2],
+        vel: &mut [egui::Vec2],
+        dt: f32,
+        accel: &mut Accelerations<'_>,
+    ) {
+        let mut acc = vec![egui::Vec2::ZERO; pos.len()];
+        accel(pos, &mut acc);
+        for ((p, v), a) in pos.iter_mut().zip(vel.iter_mut()).zip(&acc) {
+            *p += *v * dt;
+            *v += *a * dt;
+        }
+    }
+}
+
+/// First order, but kicks before moving, which keeps the energy error bounded.
+pub(crate) struct SymplecticEuler;
+
+impl Integrator for SymplecticEuler {
+    fn integrate(
+        &self,
+        pos: &mut [egui::Pos2],
+        vel: &mut [egui::Vec2],
+        dt: f32,
+        accel: &mut Accelerations<'_>,
+    ) {
+        let mut acc = vec![egui::Vec2::ZERO; pos.len()];
+        accel(pos, &mut acc);
+        for ((p, v), a) in pos.iter_mut().zip(vel.iter_mut()).zip(&acc) {
+            *v += *a * dt;
+            *p += *v * dt;
+        }
+    }
+}
+
+/// Second order and symplectic, averaging the acceleration at both ends of the step.
+pub(crate) struct VelocityVerlet;
+
+impl Integrator for VelocityVerlet {
+    fn integrate(
+        &self,
+        pos: &mut [egui::Pos2],
+        vel: &mut [egui::Vec2],
+        dt: f32,
+        accel: &mut Accelerations<'_>,
+    ) {
+        let mut start = vec![egui::Vec2::ZERO; pos.len()];
+        accel(pos, &mut start);
+        for ((p, v), a) in pos.iter_mut().zip(vel.iter()).zip(&start) {
+            *p += *v * dt + *a * (0.5 * dt * dt);
+        }
+        let mut end = vec![egui::Vec2::ZERO; pos.len()];
+        accel(pos, &mut end);
+        for ((v, a0), a1) in vel.iter_mut().zip(&start).zip(&end) {
+            *v += (*a0 + *a1) * (0.5 * dt);
+        }
+    }
+}
+
+/// Second order and symplectic, in drift-kick-drift form so it only needs one
+/// force evaluation per step.
+pub(crate) struct Leapfrog;
+
+impl Integrator for Leapfrog {
+    fn integrate(
+        &self,
+        pos: &mut [egui::Pos2],
+        vel: &mut [egui::Vec2],
+        dt: f32,
+        accel: &mut Accelerations<'_>,
+    ) {
+        for (p, v) in pos.iter_mut().zip(vel.iter()) {
+            *p += *v * (0.5 * dt);
+        }
+        let mut acc = vec![egui::Vec2::ZERO; pos.len()];
+        accel(pos, &mut acc);
+        for ((p, v), a) in pos.iter_mut().zip(vel.iter_mut()).zip(&acc) {
+            *v += *a * dt;
+            *p += *v * (0.5 * dt);
+        }
+    }
+}
+
+/// Classic fourth order Runge-Kutta. Very accurate per step, but not
+/// symplectic so energy still drifts slowly over many orbits.
+pub(crate) struct Rk4;
+
+impl Integrator for Rk4 {
+    fn integrate(
+        &self,
+        pos: &mut [egui::Pos2],
+        vel: &mut [egui::Vec2],
+        dt: f32,
+        accel: &mut Accelerations<'_>,
+    ) {
+        let n = pos.len();
+        let x0 = pos.to_vec();
+        let v0 = vel.to_vec();
+
+        let mut a1 = vec![egui::Vec2::ZERO; n];
+        accel(&x0, &mut a1);
+        let v1 = v0.clone();
+
+        let x = (0..n)
+            .map(|i| x0[i] + v1[i] * (0.5 * dt))
+            .collect::<Vec<_>>();
+        let v2 = (0..n)
+            .map(|i| v0[i] + a1[i] * (0.5 * dt))
+            .collect::<Vec<_>>();
+        let mut a2 = vec![egui::Vec2::ZERO; n];
+        accel(&x, &mut a2);
+
+        let x = (0..n)
+            .map(|i| x0[i] + v2[i] * (0.5 * dt))
+            .collect::<Vec<_>>();
+        let v3 = (0..n)
+            .map(|i| v0[i] + a2[i] * (0.5 * dt))
+            .collect::<Vec<_>>();
+        let mut a3 = vec![egui::Vec2::ZERO; n];
+        accel(&x, &mut a3);
+
+        let x = (0..n).map(|i| x0[i] + v3[i] * dt).collect::<Vec<_>>();
+        let v4 = (0..n).map(|i| v0[i] + a3[i] * dt).collect::<Vec<_>>();
+        let mut a4 = vec![egui::Vec2::ZERO; n];
+        accel(&x, &mut a4);
+
+        for i in 0..n {
+            pos[i] = x0[i] + (v1[i] + 2.0 * v2[i] + 2.0 * v3[i] + v4[i]) * (dt / 6.0);
+            vel[i] = v0[i] + (a1[i] + 2.0 * a2[i] + 2.0 * a3[i] + a4[i]) * (dt / 6.0);
+        }
+    }
+}
+
+/// The integrators that can be picked from the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum IntegratorKind {
+    ExplicitEuler,
+    SymplecticEuler,
+    #[default]
+    VelocityVerlet,
+    Leapfrog,
+    Rk4,
+}
+
+impl IntegratorKind {
+    pub const ALL: [Self; 5] = [
+        Self::ExplicitEuler,
+        Self::SymplecticEuler,
+        Self::VelocityVerlet,
+        Self::Leapfrog,
+        Self::Rk4,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ExplicitEuler => "explicit Euler",
+            Self::SymplecticEuler => "symplectic Euler",
+            Self::VelocityVerlet => "velocity Verlet",
+            Self::Leapfrog => "leapfrog",
+            Self::Rk4 => "RK4",
+        }
+    }
+
+    pub(crate) fn integrator(self) -> &'static dyn Integrator {
+        match self {
+            Self::ExplicitEuler => &ExplicitEuler,
+            Self::SymplecticEuler => &SymplecticEuler,
+            Self::VelocityVerlet => &VelocityVerlet,
+            Self::Leapfrog => &Leapfrog,
+            Self::Rk4 => &Rk4,
+        }
+    }
+}
@@ -3,8 +3,43 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+mod barnes_hut;
+mod camera;
+mod collision;
+mod diagnostics;
+mod encounter;
+mod generators;
+mod gravity;
+mod integrator;
+mod orbit;
 mod planet;
+mod plots;
+mod prediction;
+mod presets;
+mod scene;
+mod selection;
+mod simulation;
+mod timestep;
+mod trail;
+mod undo;
 pub use app::App;
+pub use camera::{Camera, Follow};
+pub use collision::CollisionMode;
+pub use diagnostics::Diagnostics;
+pub use encounter::{Encounter, EncounterKind, Watch};
+pub use generators::{Generator, MassDistribution, Pattern};
+pub use gravity::ForceSolver;
+pub use integrator::IntegratorKind;
+pub use orbit::Orbit;
+pub use planet::Planet;
+pub use prediction::{Prediction, PredictionScope};
+pub use presets::Preset;
+pub use scene::{Scene, SceneError};
+pub use selection::Selection;
+pub use simulation::{Simulation, SimulationEvent};
+pub use timestep::FixedTimestep;
+pub use trail::{FrameHistory, Trail, TrailLimit, TrailStyle};
+pub use undo::{Change, UndoStack};
 
 // ----------------------------------------------------------------------------
 // When compiling for web:
@@ -19,6 +54,14 @@ use eframe::wasm_bindgen::{self, prelude::*};
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn start(canvas_id: &str) -> Result<(), eframe::wasm_bindgen::JsValue> {
-    let app = App::default();
+    // Links to a scene carry it in the fragment, see `Scene::to_link`.
+    let fragment = web_sys::window()
+        .and_then(|window| window.location().hash().ok())
+        .unwrap_or_default();
+    let app = if fragment.len() > 1 {
+        App::from_link(&fragment)
+    } else {
+        App::default()
+    };
     eframe::start_web(canvas_id, Box::new(app))
 }
@@ -0,0 +1,126 @@
+use crate::diagnostics::cross;
+use crate::planet::Planet;
+use crate::simulation::Simulation;
+use eframe::egui;
+use std::f32::consts::{PI, TAU};
+
+/// How many points a whole drawn orbit is made of.
+const PATH_POINTS: usize = 180;
+
+/// The osculating Keplerian orbit of a planet around another: the conic it
+/// would follow if the two were alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Orbit {
+    /// The id of the planet being orbited.
+    pub attractor: i32,
+    /// Negative for hyperbolic orbits and infinite for parabolic ones.
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    /// The closest distance to the attractor.
+    pub periapsis: f32,
+    /// The furthest distance from the attractor, if the orbit is bound.
+    pub apoapsis: Option<f32>,
+    /// Seconds per revolution, if the orbit is bound.
+    pub period: Option<f32>,
+    /// The direction of periapsis, in radians.
+    pub argument_of_periapsis: f32,
+    /// Whether the planet goes around clockwise on screen, where y points down.
+    pub clockwise: bool,
+}
+
+impl Orbit {
+    /// The orbit of `planet` around `attractor` under a gravitational
+    /// constant of `g`, or none if they sit on top of each other.
+    pub fn new(planet: &Planet, attractor: &Planet, g: f32) -> Option<Self> {
+        let mu = g * (planet.mass + attractor.mass);
+        let r = planet.pos - attractor.pos;
+        let v = planet.vel - attractor.vel;
+        let dist = r.length();
+        if dist <= 0.0 || mu <= 0.0 {
+            return None;
+        }
+        let h = cross(r, v);
+        let r_dot_v = r.x * v.x + r.y * v.y;
+        let e_vec = (r * (v.length_sq() - mu / dist) - v * r_dot_v) / mu;
+        let eccentricity = e_vec.length();
+        let energy = v.length_sq() / 2.0 - mu / dist;
+        let semi_major_axis = -mu / (2.0 * energy);
+        let bound = energy < 0.0;
+        Some(Self {
+            attractor: attractor.id,
+            semi_major_axis,
+            eccentricity,
+            // Holds for every kind of conic, unlike a(1 - e).
+            periapsis: h * h / (mu * (1.0 + eccentricity)),
+            apoapsis: bound.then_some(semi_major_axis * (1.0 + eccentricity)),
+            period: bound.then(|| TAU * (semi_major_axis.powf(3.0) / mu).sqrt()),
+            // A circle has no periapsis, so measure from the planet instead.
+            argument_of_periapsis: if eccentricity > 1e-6 {
+                e_vec.angle()
+            } else {
+                r.angle()
+            },
+            clockwise: h >= 0.0,
+        })
+    }
+
+    /// Where the planet is relative to the attractor at `anomaly` radians
+    /// past periapsis, in the direction it is going.
+    pub fn offset_at(&self, anomaly: f32) -> egui::Vec2 {
+        let turn = if self.clockwise { 1.0 } else { -1.0 };
+        let semi_latus_rectum = self.periapsis * (1.0 + self.eccentricity);
+        egui::Vec2::angled(self.argument_of_periapsis + turn * anomaly) * semi_latus_rectum
+            / (1.0 + self.eccentricity * anomaly.cos())
+    }
+
+    /// Where periapsis and, if the orbit is bound, apoapsis are relative to
+    /// the attractor.
+    pub fn apsides(&self) -> (egui::Vec2, Option<egui::Vec2>) {
+        (
+            self.offset_at(0.0),
+            self.apoapsis.map(|_| self.offset_at(PI)),
+        )
+    }
+
+    /// Points along the orbit around an attractor at `center`, cut off where
+    /// it goes further out than `reach`. Whole ellipses come back around to
+    /// where they started.
+    pub fn path(&self, center: egui::Pos2, reach: f32) -> Vec<egui::Pos2> {
+        let (e, p) = (
+            self.eccentricity,
+            self.periapsis * (1.0 + self.eccentricity),
+        );
+        if self.periapsis > reach {
+            return Vec::new();
+        }
+        // Solve p / (1 + e cos ν) = reach for the anomaly where it leaves.
+        let limit = if self.apoapsis.is_some_and(|r| r <= reach) {
+            PI
+        } else {
+            ((p / reach - 1.0) / e).clamp(-1.0, 1.0).acos()
+        };
+        let steps = ((PATH_POINTS as f32 * limit / PI).ceil() as usize).max(2);
+        (0..=steps)
+            .map(|k| center + self.offset_at(-limit + 2.0 * limit * k as f32 / steps as f32))
+            .collect()
+    }
+
+    /// The orbit of the planet `id` around its [`dominant_attractor`].
+    pub fn around_dominant(simulation: &Simulation, id: i32) -> Option<Self> {
+        let planet = simulation.body(id)?;
+        let attractor = dominant_attractor(simulation, planet)?;
+        Self::new(planet, attractor, simulation.gravity.powf(2.0))
+    }
+}
+
+/// Of the planets heavier than `planet`, the one pulling on it the hardest.
+/// None for the heaviest planet around.
+pub fn dominant_attractor<'a>(simulation: &'a Simulation, planet: &Planet) -> Option<&'a Planet> {
+    simulation
+        .bodies()
+        .iter()
+        .filter(|p| p.id != planet.id && p.mass > planet.mass)
+        .map(|p| (p, p.mass / p.pos.distance_sq(planet.pos).max(f32::EPSILON)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(p, _)| p)
+}
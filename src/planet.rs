@@ -1,15 +1,27 @@
+use crate::gravity::softened_pull;
+use crate::trail::{Trail, TrailStyle};
 use eframe::egui;
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 pub struct Planet {
     pub pos: egui::Pos2,
+    /// Where the planet was before the last step, for interpolating between steps.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub prev_pos: egui::Pos2,
     pub vel: egui::Vec2,
     pub mass: f32,
     pub size: f32,
     pub id: i32,
-    pub trail: Vec<egui::Pos2>,
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub trail: Trail,
+    /// Trail settings of its own, instead of the simulation's.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub trail_style: Option<TrailStyle>,
     pub color: egui::Color32,
+    /// What the user called it, if anything.
+    #[cfg_attr(feature = "persistence", serde(default))]
+    pub name: String,
 }
 
 impl Planet {
@@ -23,39 +35,36 @@ impl Planet {
     ) -> Self {
         Self {
             pos,
+            prev_pos: pos,
             vel,
             mass,
             size,
             id,
-            trail: Vec::new(),
+            trail: Trail::default(),
+            trail_style: None,
             color: color.into(),
+            name: String::new(),
         }
     }
 
-    pub fn get_force(pos: egui::Pos2, id: i32, old: &Vec<Self>) -> egui::Vec2 {
+    /// The name to show for the planet, falling back on its id.
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("planet {}", self.id)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    pub fn get_force(pos: egui::Pos2, id: i32, old: &[Self], softening: f32) -> egui::Vec2 {
         old.iter()
             .filter(|d| d.id != id)
-            .map(|d| {
-                // (d.pos - self.pos).normalized() * dt * (self.gravity * self.mass * d.mass)
-                (d.pos - pos).normalized() * d.mass / d.pos.distance_sq(pos)
-            })
+            .map(|d| softened_pull(d.pos - pos, d.mass, softening.powf(2.0)))
             .fold(egui::Vec2::ZERO, |v1, v2| v1 + v2)
     }
 
-    pub fn update(&mut self, old: &Vec<Self>, min_trail_update: f32, gravity: f32, dt: f32) {
-        if if let Some(l) = self.trail.last() {
-            (*l - self.pos).length_sq() > min_trail_update.powf(2.0)
-        } else {
-            true
-        } {
-            self.trail.push(self.pos);
-            if self.trail.len() > 100 {
-                self.trail.remove(0);
-            }
-        }
-
-        self.pos += self.vel;
-
-        self.vel += Self::get_force(self.pos, self.id, old) * dt * gravity.powf(2.0);
+    /// The position `alpha` of the way from the last step to the current one.
+    pub fn interpolated_pos(&self, alpha: f32) -> egui::Pos2 {
+        self.prev_pos + (self.pos - self.prev_pos) * alpha
     }
 }
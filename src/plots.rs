@@ -0,0 +1,163 @@
+use crate::diagnostics::Diagnostics;
+use crate::planet::Planet;
+use crate::simulation::Simulation;
+use eframe::egui;
+use eframe::egui::widgets::plot::{Legend, Line, Plot, Value, Values};
+use std::collections::VecDeque;
+
+struct BodySample {
+    id: i32,
+    speed: f32,
+    /// Distance from the center of mass.
+    distance: f32,
+}
+
+struct Sample {
+    time: f64,
+    energy: f32,
+    momentum: f32,
+    bodies: Vec<BodySample>,
+}
+
+impl Sample {
+    fn body(&self, id: i32) -> Option<&BodySample> {
+        self.bodies.iter().find(|b| b.id == id)
+    }
+}
+
+/// A rolling record of the simulation for the plot window.
+pub struct History {
+    /// How many samples to keep before dropping the oldest.
+    pub capacity: usize,
+    /// Ids of the planets whose speed and distance get recorded.
+    pub tracked: Vec<i32>,
+    samples: VecDeque<Sample>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            capacity: 2000,
+            tracked: Vec::new(),
+            samples: VecDeque::new(),
+        }
+    }
+}
+
+impl History {
+    /// Takes a sample, unless the simulation hasn't moved since the last one.
+    pub fn record(&mut self, simulation: &Simulation) {
+        if let Some(last) = self.samples.back() {
+            if last.time == simulation.time() {
+                return;
+            }
+            if last.time > simulation.time() {
+                // Time went backwards, so this is a different run.
+                self.samples.clear();
+            }
+        }
+        let diagnostics = Diagnostics::measure(simulation);
+        let bodies = self
+            .tracked
+            .iter()
+            .filter_map(|id| simulation.body(*id))
+            .map(|p| BodySample {
+                id: p.id,
+                speed: p.vel.length(),
+                distance: p.pos.distance(diagnostics.center_of_mass),
+            })
+            .collect();
+        self.samples.push_back(Sample {
+            time: simulation.time(),
+            energy: diagnostics.energy(),
+            momentum: diagnostics.momentum.length(),
+            bodies,
+        });
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    fn line(&self, value: impl Fn(&Sample) -> Option<f32>) -> Values {
+        Values::from_values_iter(
+            self.samples
+                .iter()
+                .filter_map(|s| value(s).map(|v| Value::new(s.time, v))),
+        )
+    }
+
+    /// The contents of the plot window: which planets to track, then a chart
+    /// for each quantity.
+    pub fn ui(&mut self, ui: &mut egui::Ui, simulation: &Simulation) {
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(&mut self.capacity, 100..=10000).text("history"));
+            if ui.button("clear").clicked() {
+                self.clear();
+            }
+        });
+        egui::CollapsingHeader::new("tracked planets").show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(100.0)
+                .show(ui, |ui| {
+                    for p in simulation.bodies() {
+                        let mut tracked = self.tracked.contains(&p.id);
+                        let label = egui::Label::new(p.label()).text_color(p.color);
+                        ui.horizontal(|ui| {
+                            if ui.checkbox(&mut tracked, "").changed() {
+                                if tracked {
+                                    self.tracked.push(p.id);
+                                } else {
+                                    self.tracked.retain(|id| *id != p.id);
+                                }
+                            }
+                            ui.add(label);
+                        });
+                    }
+                });
+        });
+
+        let height = 120.0;
+        ui.label("total energy");
+        ui.add(
+            Plot::new("energy_plot")
+                .line(Line::new(self.line(|s| Some(s.energy))))
+                .height(height),
+        );
+        ui.label("momentum");
+        ui.add(
+            Plot::new("momentum_plot")
+                .line(Line::new(self.line(|s| Some(s.momentum))))
+                .height(height),
+        );
+
+        let mut speed = Plot::new("speed_plot")
+            .legend(Legend::default())
+            .height(height);
+        let mut distance = Plot::new("distance_plot")
+            .legend(Legend::default())
+            .height(height);
+        for id in &self.tracked {
+            let planet = simulation.body(*id);
+            let color = planet.map_or(egui::Color32::GRAY, |p| p.color);
+            let name = planet.map_or_else(|| format!("planet {}", id), Planet::label);
+            speed = speed.line(
+                Line::new(self.line(|s| s.body(*id).map(|b| b.speed)))
+                    .color(color)
+                    .name(&name),
+            );
+            distance = distance.line(
+                Line::new(self.line(|s| s.body(*id).map(|b| b.distance)))
+                    .color(color)
+                    .name(&name),
+            );
+        }
+        ui.label("speed");
+        ui.add(speed);
+        ui.label("distance from center of mass");
+        ui.add(distance);
+    }
+}
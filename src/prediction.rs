@@ -0,0 +1,183 @@
+use crate::camera::{Follow, Frame};
+use crate::scene::Physics;
+use crate::simulation::Simulation;
+use eframe::egui;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Simulated seconds between the points of a predicted path.
+const SAMPLE_INTERVAL: f64 = 1.0 / 30.0;
+
+/// Which bodies get their predicted paths drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PredictionScope {
+    #[default]
+    Off,
+    Selected,
+    All,
+}
+
+impl PredictionScope {
+    pub const ALL: [Self; 3] = [Self::Off, Self::Selected, Self::All];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Selected => "selected planets",
+            Self::All => "all planets",
+        }
+    }
+}
+
+/// Where every body is headed, worked out by running a copy of the
+/// simulation ahead of the live one.
+///
+/// The copy keeps pace with the live simulation and is extended a few steps
+/// further every frame instead of being redone. It only starts over from the
+/// live simulation when that gets changed in a way stepping can't account for.
+#[derive(Debug, Clone, Default)]
+pub struct Prediction {
+    /// The copy, as far ahead as the prediction reaches.
+    ahead: Option<Simulation>,
+    /// What the copy was made from, to notice when it changes.
+    revision: u64,
+    physics: Physics,
+    follow: Follow,
+    /// How far the live simulation had got at the last update.
+    live_time: f64,
+    /// The frame the paths are seen from, as of the last sample.
+    frame: Option<Frame>,
+    next_sample: f64,
+    /// Every body's path as times and positions in the frame, oldest first.
+    paths: BTreeMap<i32, VecDeque<(f64, egui::Pos2)>>,
+}
+
+impl Prediction {
+    /// Takes as many steps as `live` took since the last update, to keep
+    /// the same lead, plus up to `budget` more towards `horizon` seconds past
+    /// it. Paths are seen from the frame of `follow`, as it will be at the
+    /// time, or from the world if that is free.
+    pub fn update(&mut self, live: &Simulation, follow: &Follow, horizon: f32, budget: u32) {
+        let stale = self.ahead.is_none()
+            || live.revision() != self.revision
+            || Physics::from(live) != self.physics
+            || *follow != self.follow;
+        if stale {
+            self.restart(live, follow);
+        }
+        let kept_pace = ((live.time() - self.live_time) / live.timestep as f64).ceil();
+        let budget = budget.saturating_add(kept_pace.clamp(0.0, u32::MAX as f64) as u32);
+        self.live_time = live.time();
+        // If it still fell behind, jump to where the live one is. Everything
+        // it would have predicted up to there has already happened.
+        if let Some(ahead) = self.ahead.as_mut().filter(|a| a.time() < live.time()) {
+            *ahead = live.clone();
+            self.next_sample = live.time() + SAMPLE_INTERVAL;
+        }
+
+        // Forget what has already happened, and anything past a shorter horizon.
+        let end = live.time() + horizon as f64;
+        for path in self.paths.values_mut() {
+            while path.front().is_some_and(|(time, _)| *time < live.time()) {
+                path.pop_front();
+            }
+            while path.back().is_some_and(|(time, _)| *time > end) {
+                path.pop_back();
+            }
+        }
+        self.paths.retain(|_, path| !path.is_empty());
+
+        let Some(ahead) = &mut self.ahead else {
+            return;
+        };
+        for _ in 0..budget {
+            if ahead.time() >= end || ahead.bodies().is_empty() {
+                break;
+            }
+            ahead.tick();
+            ahead.drain_events().for_each(drop);
+            if ahead.time() >= self.next_sample {
+                record(ahead, follow, &mut self.frame, &mut self.paths);
+                self.next_sample += SAMPLE_INTERVAL;
+            }
+        }
+    }
+
+    fn restart(&mut self, live: &Simulation, follow: &Follow) {
+        let ahead = live.clone();
+        self.revision = live.revision();
+        self.physics = Physics::from(live);
+        self.follow = follow.clone();
+        self.live_time = live.time();
+        self.frame = None;
+        self.next_sample = live.time() + SAMPLE_INTERVAL;
+        self.paths.clear();
+        record(&ahead, follow, &mut self.frame, &mut self.paths);
+        self.ahead = Some(ahead);
+    }
+
+    /// The predicted path of body `id`, in the world if nothing is followed
+    /// and otherwise as seen from the followed frame, centered on zero.
+    pub fn path(&self, id: i32) -> impl Iterator<Item = egui::Pos2> + '_ {
+        self.paths
+            .get(&id)
+            .into_iter()
+            .flat_map(|path| path.iter().map(|(_, pos)| *pos))
+    }
+
+    /// The ids of the bodies there are paths for.
+    pub fn ids(&self) -> impl Iterator<Item = i32> + '_ {
+        self.paths.keys().copied()
+    }
+}
+
+/// Adds where the bodies of `ahead` are now to their paths.
+fn record(
+    ahead: &Simulation,
+    follow: &Follow,
+    frame: &mut Option<Frame>,
+    paths: &mut BTreeMap<i32, VecDeque<(f64, egui::Pos2)>>,
+) {
+    // If what's followed is gone by then, keep looking from where it was.
+    if let Some(now) = follow.frame(ahead, 1.0) {
+        *frame = Some(now);
+    }
+    for p in ahead.bodies() {
+        let pos = frame.map_or(p.pos, |f| f.to_local(p.pos));
+        paths
+            .entry(p.id)
+            .or_default()
+            .push_back((ahead.time(), pos));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planet::Planet;
+    use crate::timestep::FixedTimestep;
+
+    #[test]
+    fn reaches_ahead_when_the_live_simulation_takes_more_steps_than_the_budget() {
+        let mut live = Simulation::new(30.0, 1.0 / 1000.0);
+        for i in 0..3 {
+            let pos = egui::pos2(100.0 * i as f32, 0.0);
+            live.add_body(Planet::new(
+                pos,
+                egui::vec2(0.0, 10.0),
+                10.0,
+                5.0,
+                -1,
+                egui::Color32::WHITE,
+            ));
+        }
+        let id = live.bodies()[0].id;
+        let mut stepper = FixedTimestep::default();
+        let mut prediction = Prediction::default();
+        for _ in 0..60 {
+            stepper.advance(&mut live, 1.0 / 60.0);
+            prediction.update(&live, &Follow::Free, 5.0, 8);
+        }
+        // Eight steps a frame past the sixteen or so the live one takes.
+        assert!(prediction.path(id).count() > 10);
+    }
+}
@@ -0,0 +1,352 @@
+use crate::camera::Follow;
+use crate::gravity::ForceSolver;
+use crate::planet::Planet;
+use crate::scene::{Body, Physics, Scene, View};
+use eframe::egui;
+use rand::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+/// The ready-made scenes that can be picked from the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    #[default]
+    InnerSolarSystem,
+    BinaryStar,
+    FigureEight,
+    Lagrange,
+    GalaxyCollision,
+}
+
+impl Preset {
+    pub const ALL: [Self; 5] = [
+        Self::InnerSolarSystem,
+        Self::BinaryStar,
+        Self::FigureEight,
+        Self::Lagrange,
+        Self::GalaxyCollision,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::InnerSolarSystem => "inner solar system",
+            Self::BinaryStar => "binary star",
+            Self::FigureEight => "figure eight",
+            Self::Lagrange => "Lagrange points",
+            Self::GalaxyCollision => "galaxy collision",
+        }
+    }
+
+    /// Builds the scene around `center`.
+    pub fn scene(self, center: egui::Pos2) -> Scene {
+        match self {
+            Self::InnerSolarSystem => inner_solar_system(center),
+            Self::BinaryStar => binary_star(center),
+            Self::FigureEight => figure_eight(center),
+            Self::Lagrange => lagrange(center),
+            Self::GalaxyCollision => galaxy_collision(center),
+        }
+    }
+}
+
+/// The speed of a circular orbit at distance `r` around `mass`, for a
+/// gravitational constant of `g`.
+fn circular_speed(g: f32, mass: f32, r: f32) -> f32 {
+    (g * mass / r).sqrt()
+}
+
+/// Gives `planets` ids in order and removes their common drift, so the
+/// scene stays put on screen.
+fn scene(mut planets: Vec<Planet>, physics: Physics, follow: Follow) -> Scene {
+    let mass = planets.iter().map(|p| p.mass).sum::<f32>();
+    let drift = planets
+        .iter()
+        .fold(egui::Vec2::ZERO, |sum, p| sum + p.vel * p.mass)
+        / mass;
+    for (id, p) in planets.iter_mut().enumerate() {
+        p.id = id as i32;
+        p.vel -= drift;
+    }
+    Scene {
+        bodies: planets.iter().map(Body::from).collect(),
+        physics,
+        camera: View {
+            follow,
+            ..View::default()
+        },
+        ..Scene::default()
+    }
+}
+
+/// The Sun with Mercury, Venus, Earth and Mars on circular orbits at their
+/// relative distances, with Earth's orbit 150 px across.
+pub fn inner_solar_system(center: egui::Pos2) -> Scene {
+    let physics = Physics {
+        gravity: 30.0,
+        ..Physics::default()
+    };
+    let g = physics.gravity.powf(2.0);
+    let sun = 1000.0;
+    let mut planets = vec![Planet {
+        name: "Sun".to_owned(),
+        ..Planet::new(
+            center,
+            egui::Vec2::ZERO,
+            sun,
+            15.0,
+            0,
+            egui::Color32::from_rgb(255, 200, 60),
+        )
+    }];
+    // Name, distance in AU, mass in Earth masses, size and color.
+    let orbits = [
+        (
+            "Mercury",
+            0.39,
+            0.055,
+            3.0,
+            egui::Color32::from_rgb(160, 150, 140),
+        ),
+        (
+            "Venus",
+            0.72,
+            0.815,
+            5.0,
+            egui::Color32::from_rgb(230, 190, 120),
+        ),
+        (
+            "Earth",
+            1.0,
+            1.0,
+            5.0,
+            egui::Color32::from_rgb(70, 130, 230),
+        ),
+        (
+            "Mars",
+            1.52,
+            0.107,
+            4.0,
+            egui::Color32::from_rgb(210, 90, 50),
+        ),
+    ];
+    for (k, (name, au, mass, size, color)) in orbits.into_iter().enumerate() {
+        let r = au * 150.0;
+        // Spread them around so they don't start out lined up.
+        let dir = egui::Vec2::angled(k as f32 * 2.4);
+        planets.push(Planet {
+            name: name.to_owned(),
+            ..Planet::new(
+                center + dir * r,
+                dir.rot90() * circular_speed(g, sun + mass, r),
+                mass,
+                size,
+                0,
+                color,
+            )
+        });
+    }
+    scene(planets, physics, Follow::Body(0))
+}
+
+/// Two equal stars on a tight circular orbit, with a planet circling both
+/// far enough out to be stable.
+pub fn binary_star(center: egui::Pos2) -> Scene {
+    let physics = Physics {
+        gravity: 30.0,
+        ..Physics::default()
+    };
+    let g = physics.gravity.powf(2.0);
+    let (star, separation) = (500.0, 60.0);
+    // Each star circles the middle at half the speed of their relative orbit.
+    let speed = circular_speed(g, 2.0 * star, separation) / 2.0;
+    let r = 250.0;
+    let planets = vec![
+        Planet::new(
+            center + egui::vec2(-separation / 2.0, 0.0),
+            egui::vec2(0.0, speed),
+            star,
+            10.0,
+            0,
+            egui::Color32::from_rgb(255, 210, 120),
+        ),
+        Planet::new(
+            center + egui::vec2(separation / 2.0, 0.0),
+            egui::vec2(0.0, -speed),
+            star,
+            10.0,
+            0,
+            egui::Color32::from_rgb(255, 120, 80),
+        ),
+        Planet::new(
+            center + egui::vec2(0.0, -r),
+            egui::vec2(circular_speed(g, 2.0 * star, r), 0.0),
+            1.0,
+            4.0,
+            0,
+            egui::Color32::from_rgb(90, 200, 120),
+        ),
+    ];
+    scene(planets, physics, Follow::Barycenter)
+}
+
+/// Three equal masses chasing each other around a figure eight, from the
+/// initial conditions found by Chenciner and Montgomery.
+pub fn figure_eight(center: egui::Pos2) -> Scene {
+    let physics = Physics {
+        gravity: 30.0,
+        softening: 0.0,
+        ..Physics::default()
+    };
+    let g = physics.gravity.powf(2.0);
+    let (mass, scale) = (1000.0, 150.0);
+    // The solution is for unit masses and G = 1, so scale the velocities to
+    // keep the orbits the same shape.
+    let speed = (g * mass / scale).sqrt();
+    let pos = egui::vec2(0.970_004_4, -0.243_087_5);
+    let vel = egui::vec2(-0.932_407_4, -0.864_731_5);
+    let colors = [
+        egui::Color32::from_rgb(230, 80, 80),
+        egui::Color32::from_rgb(80, 200, 90),
+        egui::Color32::from_rgb(80, 140, 240),
+    ];
+    let planets = vec![
+        Planet::new(
+            center + pos * scale,
+            -vel / 2.0 * speed,
+            mass,
+            8.0,
+            0,
+            colors[0],
+        ),
+        Planet::new(
+            center - pos * scale,
+            -vel / 2.0 * speed,
+            mass,
+            8.0,
+            0,
+            colors[1],
+        ),
+        Planet::new(center, vel * speed, mass, 8.0, 0, colors[2]),
+    ];
+    scene(planets, physics, Follow::Barycenter)
+}
+
+/// A star and a planet with a speck of dust at each of their five Lagrange
+/// points, seen from a frame that turns with the planet. The dust at L4 and
+/// L5 stays put while the dust at L1, L2 and L3 slowly wanders off.
+pub fn lagrange(center: egui::Pos2) -> Scene {
+    let physics = Physics {
+        gravity: 30.0,
+        ..Physics::default()
+    };
+    let g = physics.gravity.powf(2.0);
+    let (star, planet, r) = (1000.0, 10.0, 200.0_f32);
+    let mu = planet / (star + planet);
+    let spin = (g * (star + planet) / r.powf(3.0)).sqrt();
+    let hill = (mu / 3.0).cbrt();
+
+    // Positions relative to the center of mass, in units of `r`, with the
+    // planet along +x.
+    let star_pos = egui::vec2(-mu, 0.0);
+    let points = [
+        (star_pos, star, 12.0, egui::Color32::from_rgb(255, 200, 60)),
+        (
+            egui::vec2(1.0 - mu, 0.0),
+            planet,
+            6.0,
+            egui::Color32::from_rgb(70, 130, 230),
+        ),
+        (
+            egui::vec2(1.0 - mu - hill, 0.0),
+            0.01,
+            2.0,
+            egui::Color32::LIGHT_RED,
+        ),
+        (
+            egui::vec2(1.0 - mu + hill, 0.0),
+            0.01,
+            2.0,
+            egui::Color32::LIGHT_RED,
+        ),
+        (
+            egui::vec2(-1.0 - 5.0 * mu / 12.0, 0.0),
+            0.01,
+            2.0,
+            egui::Color32::LIGHT_RED,
+        ),
+        (
+            star_pos + egui::Vec2::angled(PI / 3.0),
+            0.01,
+            2.0,
+            egui::Color32::LIGHT_GREEN,
+        ),
+        (
+            star_pos + egui::Vec2::angled(-PI / 3.0),
+            0.01,
+            2.0,
+            egui::Color32::LIGHT_GREEN,
+        ),
+    ];
+    let planets = points
+        .into_iter()
+        .map(|(pos, mass, size, color)| {
+            Planet::new(
+                center + pos * r,
+                pos.rot90() * r * spin,
+                mass,
+                size,
+                0,
+                color,
+            )
+        })
+        .collect();
+    scene(planets, physics, Follow::CoRotating(0, 1))
+}
+
+/// Two disk galaxies of stars around heavy cores, falling into each other
+/// off center.
+pub fn galaxy_collision(center: egui::Pos2) -> Scene {
+    let physics = Physics {
+        gravity: 30.0,
+        softening: 5.0,
+        solver: ForceSolver::BarnesHut,
+        ..Physics::default()
+    };
+    let g = physics.gravity.powf(2.0);
+    let core = 2000.0;
+    // Always the same stars, so the scene looks the same every time.
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut planets = Vec::new();
+    let galaxies = [
+        (egui::vec2(-250.0, -60.0), egui::vec2(50.0, 0.0), 1.0, 0.6),
+        (egui::vec2(250.0, 60.0), egui::vec2(-50.0, 0.0), -1.0, 0.08),
+    ];
+    for (offset, vel, turn, hue) in galaxies {
+        let core_pos = center + offset;
+        planets.push(Planet::new(
+            core_pos,
+            vel,
+            core,
+            8.0,
+            0,
+            egui::color::Hsva::new(hue, 0.3, 1.0, 1.0),
+        ));
+        for _ in 0..120 {
+            let r = rng.gen_range(30.0..120.0);
+            let dir = egui::Vec2::angled(rng.gen_range(0.0..TAU));
+            planets.push(Planet::new(
+                core_pos + dir * r,
+                vel + dir.rot90() * turn * circular_speed(g, core, r),
+                0.1,
+                1.5,
+                0,
+                egui::color::Hsva::new(
+                    hue + rng.gen_range(-0.05..0.05),
+                    rng.gen_range(0.4..0.9),
+                    rng.gen_range(0.6..1.0),
+                    1.0,
+                ),
+            ));
+        }
+    }
+    scene(planets, physics, Follow::Barycenter)
+}
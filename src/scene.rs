@@ -0,0 +1,437 @@
+use crate::camera::Follow;
+use crate::collision::CollisionMode;
+use crate::gravity::ForceSolver;
+use crate::integrator::IntegratorKind;
+use crate::planet::Planet;
+use crate::simulation::Simulation;
+use crate::trail::TrailStyle;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The scene format this build writes. Bump it and add a step to [`migrate`]
+/// whenever old files need more than new fields with defaults to keep meaning
+/// the same thing.
+pub const VERSION: u64 = 2;
+
+/// Links never inflate to more JSON than this, which is far more than any
+/// scene worth sharing needs.
+const MAX_LINK_JSON: usize = 16 << 20;
+
+/// A snapshot of a setup that can be written to a file and loaded back.
+///
+/// Files from older versions get migrated when they are read. Files from
+/// newer versions load as far as this build understands them: fields it
+/// doesn't know are ignored and fields it expects but can't find get defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub version: u64,
+    pub bodies: Vec<Body>,
+    pub physics: Physics,
+    pub camera: View,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Body {
+    pub id: i32,
+    pub pos: [f32; 2],
+    pub vel: [f32; 2],
+    pub mass: f32,
+    pub size: f32,
+    pub color: [u8; 4],
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trail: Option<TrailStyle>,
+}
+
+/// The settings of the [`Simulation`] that change how the scene plays out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Physics {
+    pub gravity: f32,
+    pub softening: f32,
+    pub timestep: f32,
+    pub integrator: IntegratorKind,
+    pub solver: ForceSolver,
+    pub theta: f32,
+    pub adaptive: bool,
+    pub tolerance: f32,
+    pub max_subdivisions: u32,
+    pub collisions: CollisionMode,
+    pub restitution: f32,
+    pub fragmentation: bool,
+    pub fragment_energy: f32,
+    pub fragment_count: u32,
+}
+
+/// What the view was looking at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct View {
+    pub follow: Follow,
+    /// The point of the world in the middle of the screen, or none to fit
+    /// every body on screen.
+    pub center: Option<[f32; 2]>,
+    /// Screen points per unit of world distance.
+    pub zoom: f32,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            follow: Follow::Free,
+            center: None,
+            zoom: 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The file has no version, so there's no telling how to read it.
+    Unversioned,
+    /// A part of the file, named here, isn't the kind of value it should be.
+    Malformed(&'static str),
+    /// The planet with this id has a position, velocity, mass or size that
+    /// isn't a finite, non-negative number where it should be.
+    InvalidBody(i32),
+    /// A link that isn't valid base64 or doesn't decompress.
+    Link,
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Json(err) => write!(f, "not a valid scene: {}", err),
+            Self::Unversioned => write!(f, "not a scene file: it has no version"),
+            Self::Malformed(part) => write!(f, "not a valid scene: {} is the wrong type", part),
+            Self::InvalidBody(id) => write!(f, "not a valid scene: planet {} is broken", id),
+            Self::Link => write!(f, "the scene link is broken"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            bodies: Vec::new(),
+            physics: Physics::default(),
+            camera: View::default(),
+        }
+    }
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self::from(&Simulation::default())
+    }
+}
+
+impl From<&Simulation> for Physics {
+    fn from(simulation: &Simulation) -> Self {
+        Self {
+            gravity: simulation.gravity,
+            softening: simulation.softening,
+            timestep: simulation.timestep,
+            integrator: simulation.integrator,
+            solver: simulation.solver,
+            theta: simulation.theta,
+            adaptive: simulation.adaptive,
+            tolerance: simulation.tolerance,
+            max_subdivisions: simulation.max_subdivisions,
+            collisions: simulation.collisions,
+            restitution: simulation.restitution,
+            fragmentation: simulation.fragmentation,
+            fragment_energy: simulation.fragment_energy,
+            fragment_count: simulation.fragment_count,
+        }
+    }
+}
+
+impl Physics {
+    /// Sets up `simulation` with these settings, kept to the ranges the UI
+    /// allows so a hand-edited file can't stall or break it.
+    pub fn apply(&self, simulation: &mut Simulation) {
+        let default = Self::default();
+        simulation.gravity = within(self.gravity, 0.0, 500.0, default.gravity);
+        simulation.softening = within(self.softening, 0.0, 50.0, default.softening);
+        simulation.timestep = within(self.timestep, 1.0 / 1000.0, 1.0 / 30.0, default.timestep);
+        simulation.integrator = self.integrator;
+        simulation.solver = self.solver;
+        simulation.theta = within(self.theta, 0.0, 1.5, default.theta);
+        simulation.adaptive = self.adaptive;
+        simulation.tolerance = within(self.tolerance, 0.001, 1.0, default.tolerance);
+        simulation.max_subdivisions = self.max_subdivisions.clamp(1, default.max_subdivisions);
+        simulation.collisions = self.collisions;
+        simulation.restitution = within(self.restitution, 0.0, 1.0, default.restitution);
+        simulation.fragmentation = self.fragmentation;
+        simulation.fragment_energy =
+            within(self.fragment_energy, 100.0, 1e6, default.fragment_energy);
+        simulation.fragment_count = self.fragment_count.clamp(2, 16);
+    }
+}
+
+/// `value` clamped to `min..=max`, or `default` if it isn't a number.
+fn within(value: f32, min: f32, max: f32, default: f32) -> f32 {
+    if value.is_nan() {
+        default
+    } else {
+        value.clamp(min, max)
+    }
+}
+
+impl Body {
+    /// Whether the body can be simulated: everything finite, and no negative
+    /// mass or size.
+    fn is_valid(&self) -> bool {
+        self.pos.iter().chain(&self.vel).all(|x| x.is_finite())
+            && self.mass.is_finite()
+            && self.mass >= 0.0
+            && self.size.is_finite()
+            && self.size > 0.0
+    }
+}
+
+impl From<&Planet> for Body {
+    fn from(planet: &Planet) -> Self {
+        Self {
+            id: planet.id,
+            pos: [planet.pos.x, planet.pos.y],
+            vel: [planet.vel.x, planet.vel.y],
+            mass: planet.mass,
+            size: planet.size,
+            color: planet.color.to_array(),
+            name: planet.name.clone(),
+            trail: planet.trail_style,
+        }
+    }
+}
+
+impl From<&Body> for Planet {
+    fn from(body: &Body) -> Self {
+        let [r, g, b, a] = body.color;
+        Planet {
+            name: body.name.clone(),
+            trail_style: body.trail,
+            ..Planet::new(
+                egui::pos2(body.pos[0], body.pos[1]),
+                egui::vec2(body.vel[0], body.vel[1]),
+                body.mass,
+                body.size,
+                body.id,
+                egui::Color32::from_rgba_premultiplied(r, g, b, a),
+            )
+        }
+    }
+}
+
+impl Scene {
+    pub fn capture(simulation: &Simulation, camera: View) -> Self {
+        Self {
+            version: VERSION,
+            // Planets that blew up would only make the scene fail to load.
+            bodies: simulation
+                .bodies()
+                .iter()
+                .map(Body::from)
+                .filter(Body::is_valid)
+                .collect(),
+            physics: Physics::from(simulation),
+            camera,
+        }
+    }
+
+    /// Replaces the bodies and settings of `simulation` with the scene's, and
+    /// returns where the camera was.
+    pub fn restore(&self, simulation: &mut Simulation) -> View {
+        simulation.clear();
+        self.physics.apply(simulation);
+        for body in &self.bodies {
+            simulation.insert_body(Planet::from(body));
+        }
+        self.camera.clone()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("scenes always serialize")
+    }
+
+    /// Reads a scene written by this or any earlier version.
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        Self::from_value(serde_json::from_str(json)?)
+    }
+
+    /// Migrates and reads a parsed scene, refusing broken planets.
+    fn from_value(value: Value) -> Result<Self, SceneError> {
+        let scene: Self = serde_json::from_value(migrate(value)?)?;
+        if let Some(body) = scene.bodies.iter().find(|b| !b.is_valid()) {
+            return Err(SceneError::InvalidBody(body.id));
+        }
+        Ok(scene)
+    }
+
+    /// The scene as deflated, URL-safe base64 JSON, short enough to put in
+    /// the fragment of a link.
+    pub fn to_link(&self) -> String {
+        let json = serde_json::to_vec(self).expect("scenes always serialize");
+        base64::encode_config(
+            miniz_oxide::deflate::compress_to_vec(&json, 9),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    /// Reads a scene made by [`Self::to_link`], with or without the leading `#`.
+    pub fn from_link(link: &str) -> Result<Self, SceneError> {
+        let compressed =
+            base64::decode_config(link.trim_start_matches('#'), base64::URL_SAFE_NO_PAD)
+                .map_err(|_| SceneError::Link)?;
+        let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_LINK_JSON)
+            .map_err(|_| SceneError::Link)?;
+        Self::from_value(serde_json::from_slice(&json)?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), SceneError> {
+        Ok(std::fs::write(path, self.to_json())?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, SceneError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Brings a scene of any version up to [`VERSION`], one version at a time.
+fn migrate(mut scene: Value) -> Result<Value, SceneError> {
+    let version = scene
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or(SceneError::Unversioned)?;
+    if version < 2 {
+        // Version 1 could only follow a single planet. Without a camera
+        // there's nothing to follow, and the default is fine.
+        match scene.get_mut("camera") {
+            Some(Value::Object(camera)) => {
+                let follow = match camera.get("followed").and_then(Value::as_i64) {
+                    Some(id) => serde_json::json!({ "Body": id }),
+                    None => "Free".into(),
+                };
+                camera.insert("follow".to_owned(), follow);
+            }
+            None | Some(Value::Null) => {}
+            Some(_) => return Err(SceneError::Malformed("camera")),
+        }
+    }
+    if version < VERSION {
+        scene["version"] = VERSION.into();
+    }
+    Ok(scene)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_followed_planet_of_version_1() {
+        let scene = Scene::from_json(r#"{"version":1,"camera":{"followed":3,"zoom":2.0}}"#)
+            .expect("version 1 scenes load");
+        assert_eq!(scene.version, VERSION);
+        assert_eq!(scene.camera.follow, Follow::Body(3));
+        assert_eq!(scene.camera.zoom, 2.0);
+
+        let scene = Scene::from_json(r#"{"version":1,"camera":{}}"#).expect("loads");
+        assert_eq!(scene.camera.follow, Follow::Free);
+        let scene = Scene::from_json(r#"{"version":1}"#).expect("loads");
+        assert_eq!(scene.camera, View::default());
+    }
+
+    #[test]
+    fn rejects_malformed_camera_of_version_1() {
+        for json in [
+            r#"{"version":1,"camera":5}"#,
+            r#"{"version":1,"camera":[1]}"#,
+        ] {
+            assert!(matches!(
+                Scene::from_json(json),
+                Err(SceneError::Malformed("camera"))
+            ));
+        }
+    }
+
+    #[test]
+    fn links_leave_out_broken_planets_and_refuse_bombs() {
+        let mut simulation = Simulation::new(30.0, 1.0 / 240.0);
+        for i in 0..3 {
+            let pos = egui::pos2(100.0 * i as f32, 0.0);
+            simulation.add_body(Planet::new(
+                pos,
+                egui::Vec2::ZERO,
+                10.0,
+                5.0,
+                -1,
+                egui::Color32::WHITE,
+            ));
+        }
+        let broken = simulation.bodies()[1].id;
+        simulation.body_mut(broken).unwrap().vel.y = f32::NAN;
+        let link = Scene::capture(&simulation, View::default()).to_link();
+        let scene = Scene::from_link(&link).expect("captured scenes load");
+        assert_eq!(scene.bodies.len(), 2);
+        assert!(scene.bodies.iter().all(|b| b.id != broken));
+
+        let bomb = base64::encode_config(
+            miniz_oxide::deflate::compress_to_vec(&vec![b' '; MAX_LINK_JSON + 1], 1),
+            base64::URL_SAFE_NO_PAD,
+        );
+        assert!(matches!(Scene::from_link(&bomb), Err(SceneError::Link)));
+    }
+
+    #[test]
+    fn rejects_broken_planets_and_clamps_physics() {
+        let body = |pos: &str, mass: &str| {
+            format!(
+                r#"{{"version":2,"bodies":[{{"id":7,"pos":{},"vel":[0,0],"mass":{},"size":1,"color":[0,0,0,255]}}]}}"#,
+                pos, mass
+            )
+        };
+        assert!(Scene::from_json(&body("[0,0]", "1")).is_ok());
+        for (pos, mass) in [("[1e39,0]", "1"), ("[0,0]", "-1"), ("[0,0]", "1e39")] {
+            assert!(matches!(
+                Scene::from_json(&body(pos, mass)),
+                Err(SceneError::InvalidBody(7))
+            ));
+        }
+
+        let scene = Scene::from_json(
+            r#"{"version":2,"physics":{"timestep":0,"tolerance":-1,"max_subdivisions":4000000000,"fragment_count":4000000000}}"#,
+        )
+        .expect("loads");
+        let mut simulation = Simulation::default();
+        scene.restore(&mut simulation);
+        assert_eq!(simulation.timestep, 1.0 / 1000.0);
+        assert_eq!(simulation.tolerance, 0.001);
+        assert_eq!(simulation.max_subdivisions, 256);
+        assert_eq!(simulation.fragment_count, 16);
+    }
+}
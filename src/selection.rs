@@ -0,0 +1,158 @@
+use crate::simulation::Simulation;
+use eframe::egui;
+
+/// The planets picked out in the UI, by id, for editing together.
+///
+/// The most recently picked one is the primary, which single-planet tools
+/// like the inspector work on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    ids: Vec<i32>,
+}
+
+impl Selection {
+    /// Just the planet `id`.
+    pub fn single(id: i32) -> Self {
+        Self { ids: vec![id] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn contains(&self, id: i32) -> bool {
+        self.ids.contains(&id)
+    }
+
+    pub fn ids(&self) -> &[i32] {
+        &self.ids
+    }
+
+    pub fn primary(&self) -> Option<i32> {
+        self.ids.last().copied()
+    }
+
+    /// Adds `id`, making it the primary.
+    pub fn insert(&mut self, id: i32) {
+        self.ids.retain(|i| *i != id);
+        self.ids.push(id);
+    }
+
+    /// Adds `id` if it isn't selected yet, and removes it if it is.
+    pub fn toggle(&mut self, id: i32) {
+        if self.contains(id) {
+            self.ids.retain(|i| *i != id);
+        } else {
+            self.ids.push(id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+
+    /// Forgets the planets that are no longer in `simulation`.
+    pub fn retain_existing(&mut self, simulation: &Simulation) {
+        self.ids.retain(|id| simulation.body(*id).is_some());
+    }
+
+    /// Selects what became of planet `old` after it merged or shattered into
+    /// `new`, if it was selected.
+    pub fn replace(&mut self, old: i32, new: &[i32]) {
+        if self.contains(old) {
+            self.ids.retain(|id| *id != old);
+            for id in new {
+                self.insert(*id);
+            }
+        }
+    }
+
+    /// The total mass, center of mass and its velocity of the selected
+    /// planets, or none if they weigh nothing.
+    pub fn center_of_mass(&self, simulation: &Simulation) -> Option<(f32, egui::Pos2, egui::Vec2)> {
+        let (mass, pos, vel) = simulation
+            .bodies()
+            .iter()
+            .filter(|p| self.contains(p.id))
+            .fold(
+                (0.0, egui::Vec2::ZERO, egui::Vec2::ZERO),
+                |(mass, pos, vel), p| {
+                    (
+                        mass + p.mass,
+                        pos + p.pos.to_vec2() * p.mass,
+                        vel + p.vel * p.mass,
+                    )
+                },
+            );
+        (mass > 0.0).then(|| (mass, (pos / mass).to_pos2(), vel / mass))
+    }
+
+    /// Moves every selected planet by `delta`, straight there.
+    pub fn translate(&self, simulation: &mut Simulation, delta: egui::Vec2) {
+        for p in simulation.bodies_mut() {
+            if self.contains(p.id) {
+                p.pos += delta;
+                p.prev_pos = p.pos;
+                p.trail.clear();
+            }
+        }
+    }
+
+    pub fn add_velocity(&self, simulation: &mut Simulation, delta: egui::Vec2) {
+        for p in simulation.bodies_mut() {
+            if self.contains(p.id) {
+                p.vel += delta;
+            }
+        }
+    }
+
+    pub fn set_mass(&self, simulation: &mut Simulation, mass: f32) {
+        for p in simulation.bodies_mut() {
+            if self.contains(p.id) {
+                p.mass = mass;
+            }
+        }
+    }
+
+    pub fn recolor(&self, simulation: &mut Simulation, color: egui::Color32) {
+        for p in simulation.bodies_mut() {
+            if self.contains(p.id) {
+                p.color = color;
+            }
+        }
+    }
+
+    /// Removes every selected planet from `simulation` and empties the
+    /// selection.
+    pub fn delete(&mut self, simulation: &mut Simulation) {
+        for id in self.ids.drain(..) {
+            simulation.remove_body(id);
+        }
+    }
+
+    /// Gives the selected planets, as a whole, the velocity of the center of
+    /// mass of everything else, so the group stops drifting away from the
+    /// rest of the system. How they move relative to each other stays the same.
+    pub fn make_bound(&self, simulation: &mut Simulation) {
+        let Some((_, _, vel)) = self.center_of_mass(simulation) else {
+            return;
+        };
+        let (rest_mass, rest_momentum) = simulation
+            .bodies()
+            .iter()
+            .filter(|p| !self.contains(p.id))
+            .fold((0.0, egui::Vec2::ZERO), |(mass, momentum), p| {
+                (mass + p.mass, momentum + p.vel * p.mass)
+            });
+        let rest_vel = if rest_mass > 0.0 {
+            rest_momentum / rest_mass
+        } else {
+            egui::Vec2::ZERO
+        };
+        self.add_velocity(simulation, rest_vel - vel);
+    }
+}
@@ -0,0 +1,395 @@
+use crate::collision::{self, CollisionMode, Fragmentation};
+use crate::gravity::{Field, ForceSolver, Gravity};
+use crate::integrator::IntegratorKind;
+use crate::planet::Planet;
+use crate::trail::TrailStyle;
+use eframe::egui;
+
+/// The n-body physics, independent of any egui context.
+///
+/// Owns the bodies and the global parameters of the simulation so the same
+/// physics can be driven from the app, from tests or from scripts.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "persistence", serde(default))]
+pub struct Simulation {
+    bodies: Vec<Planet>,
+    next_id: i32,
+    time: f64,
+    /// The gravitational constant is the square of this.
+    pub gravity: f32,
+    /// Plummer softening length, which keeps the pull between bodies finite
+    /// as they approach each other.
+    pub softening: f32,
+    pub solver: ForceSolver,
+    /// Opening angle of the Barnes-Hut solver; bigger is faster but coarser.
+    pub theta: f32,
+    /// Evaluate forces on all cores. Only has an effect on native builds with
+    /// the `parallel` feature.
+    pub parallel: bool,
+    /// Make parallel force evaluation give bit-identical results to serial.
+    pub deterministic: bool,
+    pub timestep: f32,
+    /// How far a body moves before its trail gets another point.
+    pub min_trail_update: f32,
+    /// How the trails of bodies without settings of their own behave.
+    pub trail: TrailStyle,
+    pub integrator: IntegratorKind,
+    /// Split each step into shorter substeps when bodies get close or move fast.
+    pub adaptive: bool,
+    /// Fraction of the time it takes a body to fall across its own size, or
+    /// the softening length if that is longer, that a single adaptive substep
+    /// may last.
+    pub tolerance: f32,
+    /// Never split a step into more than this many substeps.
+    pub max_subdivisions: u32,
+    pub collisions: CollisionMode,
+    /// Fraction of the approach speed that bouncing planets separate with.
+    pub restitution: f32,
+    /// Break merging planets into pieces when they hit hard enough.
+    pub fragmentation: bool,
+    /// Impact energy per unit of mass above which planets break up.
+    pub fragment_energy: f32,
+    pub fragment_count: u32,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    substeps: u32,
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    events: Vec<SimulationEvent>,
+    /// Counts the changes made to the bodies from outside, as opposed to by
+    /// stepping.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    revision: u64,
+}
+
+/// Something that happened to the bodies during a step, for the UI to report.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationEvent {
+    /// The body's position or velocity stopped being finite, so it was removed.
+    Corrupted { id: i32 },
+    /// Two bodies collided and `absorbed` became part of `survivor`.
+    Merged {
+        survivor: i32,
+        absorbed: i32,
+        pos: egui::Pos2,
+    },
+    /// Two bodies hit hard enough to break into `fragments`.
+    Fragmented {
+        parents: [i32; 2],
+        fragments: Vec<i32>,
+        pos: egui::Pos2,
+    },
+    /// Two bodies touched and bounced off each other at `pos`.
+    Bounced { ids: [i32; 2], pos: egui::Pos2 },
+}
+
+impl std::fmt::Display for SimulationEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Corrupted { id } => write!(f, "planet {} blew up and was removed", id),
+            Self::Merged {
+                survivor, absorbed, ..
+            } => write!(f, "planet {} merged into planet {}", absorbed, survivor),
+            Self::Fragmented {
+                parents, fragments, ..
+            } => write!(
+                f,
+                "planets {} and {} shattered into {} pieces",
+                parents[0],
+                parents[1],
+                fragments.len()
+            ),
+            Self::Bounced { ids, .. } => {
+                write!(
+                    f,
+                    "planets {} and {} bounced off each other",
+                    ids[0], ids[1]
+                )
+            }
+        }
+    }
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new(150.0, 1.0 / 240.0)
+    }
+}
+
+impl Simulation {
+    pub fn new(gravity: f32, timestep: f32) -> Self {
+        Self {
+            bodies: Vec::new(),
+            next_id: 0,
+            time: 0.0,
+            gravity,
+            softening: 1.0,
+            solver: ForceSolver::default(),
+            theta: 0.5,
+            parallel: true,
+            deterministic: true,
+            timestep,
+            min_trail_update: 0.1,
+            trail: TrailStyle::default(),
+            integrator: IntegratorKind::default(),
+            adaptive: false,
+            tolerance: 0.05,
+            max_subdivisions: 256,
+            collisions: CollisionMode::default(),
+            restitution: 0.8,
+            fragmentation: false,
+            fragment_energy: 20000.0,
+            fragment_count: 6,
+            substeps: 0,
+            events: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    pub fn bodies(&self) -> &[Planet] {
+        &self.bodies
+    }
+
+    pub fn bodies_mut(&mut self) -> &mut [Planet] {
+        self.revision += 1;
+        &mut self.bodies
+    }
+
+    pub fn body(&self, id: i32) -> Option<&Planet> {
+        self.bodies.iter().find(|p| p.id == id)
+    }
+
+    pub fn body_mut(&mut self, id: i32) -> Option<&mut Planet> {
+        self.revision += 1;
+        self.bodies.iter_mut().find(|p| p.id == id)
+    }
+
+    /// Adds `planet` to the simulation under a fresh id and returns that id.
+    pub fn add_body(&mut self, mut planet: Planet) -> i32 {
+        planet.id = self.next_id;
+        self.next_id += 1;
+        self.revision += 1;
+        self.bodies.push(planet);
+        self.next_id - 1
+    }
+
+    /// Adds `planet` keeping the id it already has, e.g. when restoring a
+    /// saved scene. Ids handed out later start above it.
+    pub fn insert_body(&mut self, planet: Planet) {
+        self.next_id = self.next_id.max(planet.id + 1);
+        self.revision += 1;
+        self.bodies.push(planet);
+    }
+
+    /// Swaps every body for `bodies`, e.g. to go back to an earlier state,
+    /// without rewinding the clock. Ids are never handed out twice, even if
+    /// their bodies are gone.
+    pub fn replace_bodies(&mut self, bodies: Vec<Planet>) {
+        self.bodies.clear();
+        self.revision += 1;
+        for planet in bodies {
+            self.insert_body(Planet {
+                prev_pos: planet.pos,
+                ..planet
+            });
+        }
+    }
+
+    pub fn remove_body(&mut self, id: i32) -> Option<Planet> {
+        let idx = self.bodies.iter().position(|p| p.id == id)?;
+        self.revision += 1;
+        Some(self.bodies.remove(idx))
+    }
+
+    pub fn clear(&mut self) {
+        self.bodies.clear();
+        self.time = 0.0;
+        self.revision += 1;
+    }
+
+    /// Goes up whenever the bodies are added, removed or handed out for
+    /// changing, so anything worked out from them knows to start over.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// How many seconds have been simulated since the start or the last clear.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn force_law(&self) -> Gravity {
+        Gravity {
+            g: self.gravity.powf(2.0),
+            eps_sq: self.softening.powf(2.0),
+            solver: self.solver,
+            theta: self.theta,
+            parallel: self.parallel,
+            deterministic: self.deterministic,
+        }
+    }
+
+    /// The exact acceleration a massless test particle at `pos` would feel.
+    pub fn acceleration_at(&self, pos: egui::Pos2) -> egui::Vec2 {
+        Planet::get_force(pos, -1, &self.bodies, self.softening) * self.gravity.powf(2.0)
+    }
+
+    /// The gravitational field of the bodies as they are now, for sampling at
+    /// many points with the current solver.
+    pub fn field(&self) -> Field {
+        self.force_law().field(
+            self.bodies.iter().map(|p| p.mass).collect(),
+            self.bodies.iter().map(|p| p.pos).collect(),
+        )
+    }
+
+    /// Advances every body by `dt` seconds.
+    pub fn step(&mut self, dt: f32) {
+        for p in &mut self.bodies {
+            let style = p.trail_style.unwrap_or(self.trail);
+            p.trail
+                .record(self.time, p.pos, self.min_trail_update, &style);
+            p.prev_pos = p.pos;
+        }
+
+        let mut pos = self.bodies.iter().map(|p| p.pos).collect::<Vec<_>>();
+        let mut vel = self.bodies.iter().map(|p| p.vel).collect::<Vec<_>>();
+        let masses = self.bodies.iter().map(|p| p.mass).collect::<Vec<_>>();
+        let gravity = self.force_law();
+        let integrator = self.integrator.integrator();
+        let mut accel =
+            |pos: &[egui::Pos2], acc: &mut [egui::Vec2]| gravity.accelerations(&masses, pos, acc);
+        if self.adaptive {
+            let min_step = dt / self.max_subdivisions.max(1) as f32;
+            let lengths = self
+                .bodies
+                .iter()
+                .map(|p| p.size.max(self.softening))
+                .collect::<Vec<_>>();
+            let mut sized_at = pos.clone();
+            let mut acc = vec![egui::Vec2::ZERO; pos.len()];
+            let mut remaining = dt;
+            self.substeps = 0;
+            while remaining > min_step * 1e-3 {
+                sized_at.copy_from_slice(&pos);
+                accel(&sized_at, &mut acc);
+                let h = (self.tolerance * encounter_time(&lengths, &acc))
+                    .max(min_step)
+                    .min(remaining);
+                // Most integrators start with the forces just used for sizing
+                // the step, so hand those over instead of working them out again.
+                let mut fresh = true;
+                let mut reuse = |at: &[egui::Pos2], out: &mut [egui::Vec2]| {
+                    if std::mem::take(&mut fresh) && at == sized_at.as_slice() {
+                        out.copy_from_slice(&acc);
+                    } else {
+                        accel(at, out);
+                    }
+                };
+                integrator.integrate(&mut pos, &mut vel, h, &mut reuse);
+                remaining -= h;
+                self.substeps += 1;
+            }
+        } else {
+            integrator.integrate(&mut pos, &mut vel, dt, &mut accel);
+            self.substeps = 1;
+        }
+        for ((p, pos), vel) in self.bodies.iter_mut().zip(pos).zip(vel) {
+            p.pos = pos;
+            p.vel = vel;
+        }
+        let fragmentation = Some(Fragmentation {
+            energy: self.fragment_energy,
+            count: self.fragment_count,
+            retained: self.restitution.powf(2.0),
+        })
+        .filter(|_| self.fragmentation);
+        self.time += dt as f64;
+        collision::resolve(
+            &mut self.bodies,
+            self.collisions,
+            self.restitution,
+            fragmentation,
+            &mut self.next_id,
+            &mut self.events,
+        );
+
+        // Gravity leaves out a body that blew up, so it can't drag the others
+        // with it, but get rid of it before anything else trips over it.
+        let events = &mut self.events;
+        self.bodies.retain(|p| {
+            let finite = p.pos.x.is_finite()
+                && p.pos.y.is_finite()
+                && p.vel.x.is_finite()
+                && p.vel.y.is_finite();
+            if !finite {
+                events.push(SimulationEvent::Corrupted { id: p.id });
+            }
+            finite
+        });
+    }
+
+    /// Takes the events that happened since the last call.
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, SimulationEvent> {
+        self.events.drain(..)
+    }
+
+    /// How many substeps the last call to [`Self::step`] was split into.
+    pub fn substeps(&self) -> u32 {
+        self.substeps
+    }
+
+    /// Advances every body by one `timestep`.
+    pub fn tick(&mut self) {
+        self.step(self.timestep);
+    }
+}
+
+/// The shortest time scale on which any body's surroundings change: how long
+/// it would take to fall across its own length, from rest, at its current
+/// acceleration. That shrinks along with the distance in a close encounter,
+/// without having to look at every pair of bodies.
+fn encounter_time(lengths: &[f32], acc: &[egui::Vec2]) -> f32 {
+    lengths
+        .iter()
+        .zip(acc)
+        .map(|(length, a)| (length / a.length()).sqrt())
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_corrupted_body_leaves_the_others_finite() {
+        for integrator in IntegratorKind::ALL {
+            for solver in ForceSolver::ALL {
+                let mut simulation = Simulation::new(30.0, 1.0 / 240.0);
+                simulation.integrator = integrator;
+                simulation.solver = solver;
+                for i in 0..4 {
+                    let pos = egui::pos2(100.0 * i as f32, 0.0);
+                    let vel = egui::vec2(0.0, 10.0 * i as f32);
+                    simulation.add_body(Planet::new(pos, vel, 10.0, 5.0, -1, egui::Color32::WHITE));
+                }
+                let broken = simulation.bodies()[1].id;
+                simulation.body_mut(broken).unwrap().pos.x = f32::INFINITY;
+                simulation.tick();
+
+                let events = simulation.drain_events().collect::<Vec<_>>();
+                assert_eq!(
+                    events,
+                    [SimulationEvent::Corrupted { id: broken }],
+                    "{} with {}",
+                    integrator.name(),
+                    solver.name()
+                );
+                assert_eq!(simulation.bodies().len(), 3);
+                assert!(simulation
+                    .bodies()
+                    .iter()
+                    .all(|p| p.pos.x.is_finite() && p.pos.y.is_finite() && p.vel.x.is_finite()));
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::simulation::Simulation;
+
+/// Runs a [`Simulation`] in steps of its fixed `timestep`, however long the
+/// frames in between happen to be, so the result doesn't depend on the
+/// refresh rate of the monitor.
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    /// Upper bound on the steps taken per call to [`Self::advance`]. Time
+    /// beyond that is dropped so a lag spike can't snowball into longer frames.
+    pub max_substeps: u32,
+    accumulator: f32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(max_substeps: u32) -> Self {
+        Self {
+            max_substeps,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Takes as many whole steps as fit into the time left over from last
+    /// call plus `elapsed`, and returns how far into the next step the
+    /// simulation is, for interpolating what gets drawn.
+    pub fn advance(&mut self, simulation: &mut Simulation, elapsed: f32) -> f32 {
+        let dt = simulation.timestep;
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= dt {
+            if steps >= self.max_substeps {
+                self.accumulator %= dt;
+                break;
+            }
+            simulation.step(dt);
+            self.accumulator -= dt;
+            steps += 1;
+        }
+        self.alpha(dt)
+    }
+
+    /// The fraction of a step of length `dt` that has built up.
+    pub fn alpha(&self, dt: f32) -> f32 {
+        (self.accumulator / dt).clamp(0.0, 1.0)
+    }
+
+    /// Throws away any partial step, e.g. while paused.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
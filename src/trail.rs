@@ -0,0 +1,186 @@
+use crate::camera::{Follow, Frame};
+use eframe::egui;
+use std::collections::VecDeque;
+use std::f32::consts::{PI, TAU};
+
+/// Trails never hold more points than this, however long they may last.
+const MAX_POINTS: usize = 10_000;
+
+/// What decides how long a trail gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum TrailLimit {
+    /// Keeps a number of points.
+    #[default]
+    Points,
+    /// Keeps the points of the last so many seconds.
+    Seconds,
+}
+
+impl TrailLimit {
+    pub const ALL: [Self; 2] = [Self::Points, Self::Seconds];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Points => "points",
+            Self::Seconds => "seconds",
+        }
+    }
+}
+
+/// How long trails get and how they are drawn, for every planet or just one.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TrailStyle {
+    pub visible: bool,
+    pub limit: TrailLimit,
+    pub max_points: usize,
+    pub max_seconds: f32,
+    /// Width of the newest end, in screen points.
+    pub width: f32,
+    /// Fade the older end out.
+    pub fade: bool,
+    /// Narrow the older end down.
+    pub taper: bool,
+}
+
+impl Default for TrailStyle {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            limit: TrailLimit::Points,
+            max_points: 100,
+            max_seconds: 5.0,
+            width: 2.0,
+            fade: false,
+            taper: false,
+        }
+    }
+}
+
+impl TrailStyle {
+    /// How much is left of the point recorded at `time`, `behind` points back
+    /// from the newest, at `now`: one for new points, down to zero for points
+    /// about to be dropped.
+    pub fn freshness(&self, behind: usize, time: f64, now: f64) -> f32 {
+        let age = match self.limit {
+            TrailLimit::Points => behind as f32 / self.max_points.max(1) as f32,
+            TrailLimit::Seconds => (now - time) as f32 / self.max_seconds.max(f32::EPSILON),
+        };
+        (1.0 - age).clamp(0.0, 1.0)
+    }
+
+    /// The stroke of a trail of `color` where it is as fresh as `freshness`.
+    pub fn stroke(&self, freshness: f32, color: egui::Color32) -> egui::Stroke {
+        let alpha = if self.fade { freshness } else { 1.0 };
+        let width = if self.taper {
+            self.width * freshness
+        } else {
+            self.width
+        };
+        egui::Stroke::new(width, color.linear_multiply(alpha))
+    }
+}
+
+/// Where a planet has been, as world positions and the simulation time it
+/// was at each, oldest first.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
+pub struct Trail {
+    points: VecDeque<(f64, egui::Pos2)>,
+}
+
+impl Trail {
+    /// Adds `pos` at `time` if it is more than `spacing` away from the last
+    /// point, and drops the points that `style` no longer keeps.
+    pub fn record(&mut self, time: f64, pos: egui::Pos2, spacing: f32, style: &TrailStyle) {
+        if self
+            .points
+            .back()
+            .is_none_or(|(_, last)| last.distance_sq(pos) > spacing.powf(2.0))
+        {
+            self.points.push_back((time, pos));
+        }
+        let max_points = match style.limit {
+            TrailLimit::Points => style.max_points.min(MAX_POINTS),
+            TrailLimit::Seconds => MAX_POINTS,
+        };
+        while self.points.len() > max_points {
+            self.points.pop_front();
+        }
+        if style.limit == TrailLimit::Seconds {
+            let start = time - style.max_seconds as f64;
+            while self.points.front().is_some_and(|(t, _)| *t < start) {
+                self.points.pop_front();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// The points with the time of each, oldest first.
+    pub fn points(&self) -> impl Iterator<Item = (f64, egui::Pos2)> + '_ {
+        self.points.iter().copied()
+    }
+
+    /// The time of the oldest point.
+    pub fn start(&self) -> Option<f64> {
+        self.points.front().map(|(time, _)| *time)
+    }
+}
+
+/// Where a followed frame was over time, for drawing trails as seen from it.
+#[derive(Debug, Clone, Default)]
+pub struct FrameHistory {
+    follow: Follow,
+    frames: VecDeque<(f64, Frame)>,
+}
+
+impl FrameHistory {
+    /// Remembers that the frame of `follow` was `frame` at `time`. Following
+    /// something else starts over, and going back in time forgets everything
+    /// after.
+    pub fn record(&mut self, time: f64, follow: &Follow, frame: Frame) {
+        if *follow != self.follow {
+            self.follow = follow.clone();
+            self.frames.clear();
+        }
+        while self.frames.back().is_some_and(|(t, _)| *t >= time) {
+            self.frames.pop_back();
+        }
+        self.frames.push_back((time, frame));
+    }
+
+    /// Forgets where the frame was before `time`, except for what is needed
+    /// to tell where it was at `time`.
+    pub fn forget_before(&mut self, time: f64) {
+        while self.frames.get(1).is_some_and(|(t, _)| *t <= time) {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Where the frame was at `time`, in between the closest two times it was
+    /// recorded at. None if that was before or after what is remembered.
+    pub fn at(&self, time: f64) -> Option<Frame> {
+        let after = self.frames.partition_point(|(t, _)| *t < time);
+        let (t1, to) = *self.frames.get(after)?;
+        if t1 == time || after == 0 {
+            return (t1 == time).then_some(to);
+        }
+        let (t0, from) = self.frames[after - 1];
+        let s = ((time - t0) / (t1 - t0)) as f32;
+        // Turn the short way round, in case the angle wrapped.
+        let turn = (to.angle - from.angle + PI).rem_euclid(TAU) - PI;
+        Some(Frame {
+            origin: from.origin + (to.origin - from.origin) * s,
+            vel: from.vel + (to.vel - from.vel) * s,
+            angle: from.angle + turn * s,
+            spin: from.spin + (to.spin - from.spin) * s,
+        })
+    }
+}
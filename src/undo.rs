@@ -0,0 +1,156 @@
+use crate::planet::Planet;
+use crate::scene::Physics;
+use crate::simulation::Simulation;
+use crate::trail::Trail;
+
+/// How many changes can be undone before the oldest are forgotten.
+const MAX_UNDO: usize = 100;
+
+/// Edits of the same kind to the same planet less than this many seconds
+/// apart undo together, so dragging a value or typing a name is one step.
+const MERGE_WINDOW: f64 = 1.0;
+
+/// Something done to the planets that can be undone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Create,
+    Spawn,
+    Delete,
+    Duplicate(i32),
+    /// Changed in the inspector.
+    Edit(i32),
+    /// Several selected planets changed together.
+    Group,
+    /// Selected planets made to stop drifting away.
+    Bind,
+    /// Dragged somewhere else or re-aimed on the canvas.
+    Drag(i32),
+    Reset,
+    Preset,
+    Load,
+}
+
+impl Change {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Spawn => "spawn",
+            Self::Delete => "delete",
+            Self::Duplicate(_) => "duplicate",
+            Self::Edit(_) => "edit",
+            Self::Group => "group edit",
+            Self::Bind => "make bound",
+            Self::Drag(_) => "drag",
+            Self::Reset => "reset",
+            Self::Preset => "preset",
+            Self::Load => "load",
+        }
+    }
+
+    /// Whether the change also swaps out the settings of the simulation,
+    /// rather than just its planets.
+    fn replaces_physics(self) -> bool {
+        matches!(self, Self::Preset | Self::Load)
+    }
+}
+
+/// The planets, and if the change replaced them the settings, from before or
+/// after a change. Trails are left out: they can hold thousands of points per
+/// planet, and would be stale by the time the snapshot is restored anyway.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    change: Change,
+    bodies: Vec<Planet>,
+    physics: Option<Physics>,
+    time: f64,
+}
+
+impl Snapshot {
+    fn take(change: Change, simulation: &Simulation, time: f64) -> Self {
+        Self {
+            change,
+            bodies: simulation
+                .bodies()
+                .iter()
+                .map(|p| Planet {
+                    pos: p.pos,
+                    prev_pos: p.prev_pos,
+                    vel: p.vel,
+                    mass: p.mass,
+                    size: p.size,
+                    id: p.id,
+                    trail: Trail::default(),
+                    color: p.color,
+                    name: p.name.clone(),
+                    trail_style: p.trail_style,
+                })
+                .collect(),
+            physics: change.replaces_physics().then(|| Physics::from(simulation)),
+            time,
+        }
+    }
+
+    fn restore(self, simulation: &mut Simulation) {
+        simulation.replace_bodies(self.bodies);
+        if let Some(physics) = self.physics {
+            physics.apply(simulation);
+        }
+    }
+}
+
+/// Undo and redo stacks of the changes made to the planets.
+#[derive(Debug, Clone, Default)]
+pub struct UndoStack {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl UndoStack {
+    /// Remembers `simulation` as it is right before `change`, made at `time`
+    /// seconds, and forgets everything that was undone.
+    pub fn record(&mut self, change: Change, simulation: &Simulation, time: f64) {
+        self.redo.clear();
+        if let Some(last) = self.undo.last_mut() {
+            let mergeable = matches!(change, Change::Edit(_) | Change::Group);
+            if mergeable && last.change == change && time - last.time < MERGE_WINDOW {
+                last.time = time;
+                return;
+            }
+        }
+        if self.undo.len() >= MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(Snapshot::take(change, simulation, time));
+    }
+
+    /// Puts `simulation` back the way it was before the last change, and
+    /// returns which change that was.
+    pub fn undo(&mut self, simulation: &mut Simulation) -> Option<Change> {
+        let before = self.undo.pop()?;
+        let change = before.change;
+        self.redo
+            .push(Snapshot::take(change, simulation, before.time));
+        before.restore(simulation);
+        Some(change)
+    }
+
+    /// Makes the last undone change again, and returns which change it was.
+    pub fn redo(&mut self, simulation: &mut Simulation) -> Option<Change> {
+        let after = self.redo.pop()?;
+        let change = after.change;
+        self.undo
+            .push(Snapshot::take(change, simulation, after.time));
+        after.restore(simulation);
+        Some(change)
+    }
+
+    /// The change [`Self::undo`] would undo.
+    pub fn next_undo(&self) -> Option<Change> {
+        self.undo.last().map(|s| s.change)
+    }
+
+    /// The change [`Self::redo`] would redo.
+    pub fn next_redo(&self) -> Option<Change> {
+        self.redo.last().map(|s| s.change)
+    }
+}